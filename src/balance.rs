@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    circle::Circle,
+    circles_packing::CirclesPacking,
+    container::Container,
+    point::Point,
+    utils::{Float, FloatType},
+};
+
+/// Constraint on the centre of mass of inner circles, which has to stay near the container
+/// centre (0, 0), e.g. for rotating machinery layouts.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Balance<T: Float = FloatType> {
+    /// Allowed distance between the centre of mass and the container centre.
+    #[serde(default)]
+    pub max_offset: T,
+    /// Mass of every inner circle, circles areas are used when empty. Validation treats masses
+    /// of a different length as an infinite imbalance.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub masses: Vec<T>,
+}
+
+impl<T: Float> Balance<T> {
+    pub fn new(max_offset: T) -> Self {
+        Self {
+            max_offset,
+            masses: Vec::new(),
+        }
+    }
+
+    pub fn with_masses(mut self, masses: Vec<T>) -> Self {
+        self.masses = masses;
+        self
+    }
+
+    /// Whether masses are areas or there is one for each of `circles`.
+    pub fn fits(&self, circles: usize) -> bool {
+        self.masses.is_empty() || self.masses.len() == circles
+    }
+
+    /// Masses of circles with the given radiuses.
+    pub fn masses(&self, radiuses: &[T]) -> Vec<T> {
+        if self.masses.is_empty() {
+            radiuses
+                .iter()
+                .map(|radius| T::pi() * radius.clone() * radius.clone())
+                .collect()
+        } else {
+            self.masses.clone()
+        }
+    }
+
+    /// Centre of mass of circles with centers, (0, 0) if there are none.
+    pub fn center_of_mass(&self, circles: &[Circle<T>]) -> Point<T> {
+        let radiuses: Vec<T> = circles.iter().map(|circle| circle.radius.clone()).collect();
+        let (mut total_mass, mut x, mut y) = (T::zero(), T::zero(), T::zero());
+
+        for (circle, mass) in circles.iter().zip(self.masses(&radiuses)) {
+            if let Some(center) = &circle.center {
+                total_mass += mass.clone();
+                x += mass.clone() * center.x.clone();
+                y += mass * center.y.clone();
+            }
+        }
+
+        if total_mass > T::zero() {
+            Point {
+                x: x / total_mass.clone(),
+                y: y / total_mass,
+            }
+        } else {
+            Point {
+                x: T::zero(),
+                y: T::zero(),
+            }
+        }
+    }
+
+    /// Distance between the centre of mass of circles and the container centre.
+    pub fn imbalance(&self, circles: &[Circle<T>]) -> T {
+        let center = self.center_of_mass(circles);
+        center.x.hypot(center.y)
+    }
+}
+
+/// Shift movable circles, so that their centre of mass gets to the container centre, and grow
+/// the container to hold them.
+///
+/// Gives the refinement of a balanced packing a feasible start, overlaps with fixed circles and
+/// obstacles are left to the refinement.
+pub fn recentre<T: Float>(packing: &CirclesPacking<T>, balance: &Balance<T>) -> CirclesPacking<T> {
+    let masses = balance.masses(&packing.inner_circles_radiuses());
+    let center = balance.center_of_mass(&packing.inner_circles);
+
+    let (mut total_mass, mut movable_mass) = (T::zero(), T::zero());
+    for (i, (circle, mass)) in packing.inner_circles.iter().zip(&masses).enumerate() {
+        if circle.center.is_some() {
+            total_mass += mass.clone();
+            if !packing.is_fixed(i) {
+                movable_mass += mass.clone();
+            }
+        }
+    }
+    let scale = if movable_mass > T::zero() {
+        total_mass / movable_mass
+    } else {
+        T::zero()
+    };
+
+    let inner_circles: Vec<Circle<T>> = packing
+        .inner_circles
+        .iter()
+        .enumerate()
+        .map(|(i, circle)| match &circle.center {
+            Some(point) if !packing.is_fixed(i) => Circle::new(
+                circle.radius.clone(),
+                Point {
+                    x: point.x.clone() - scale.clone() * center.x.clone(),
+                    y: point.y.clone() - scale.clone() * center.y.clone(),
+                },
+            ),
+            _ => circle.clone(),
+        })
+        .collect();
+
+    let container = &packing.container;
+    let size = inner_circles
+        .iter()
+        .filter_map(|circle| {
+            Some(container.required_size(
+                circle.center.clone()?,
+                circle.radius.clone() + packing.clearance.wall.clone(),
+            ))
+        })
+        .fold(container.size.clone(), T::max);
+
+    CirclesPacking::new(Container::new(container.shape.clone(), size), inner_circles)
+        .with_original_indexes(packing.original_indexes.clone())
+        .with_fixed(packing.fixed.clone())
+        .with_obstacles(packing.obstacles.clone())
+        .with_clearance(packing.clearance.clone())
+        .with_balance(Some(balance.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        calcfg::CirclesPackingObjective, dichotomy_step_ralgo::dichotomy_step_ralgo,
+        heuristic_algo::HeuristicAlgorithmBuilder, ralgo::RAlgorithmBuilder,
+    };
+
+    fn circle(radius: FloatType, x: FloatType, y: FloatType) -> Circle {
+        Circle::new(radius, Point { x, y })
+    }
+
+    #[test]
+    fn recentred_packing_is_balanced() {
+        let packing = CirclesPacking::new(
+            Container::circle(6.0),
+            vec![
+                circle(1.0, 1.0, 2.0),
+                circle(2.0, 2.5, -1.0),
+                circle(1.0, -4.0, 0.0),
+            ],
+        )
+        .with_fixed(vec![false, false, true]);
+        let balance = Balance::new(0.0);
+        assert!(balance.imbalance(&packing.inner_circles) > 0.5);
+
+        let recentred = recentre(&packing, &balance);
+
+        assert!(balance.imbalance(&recentred.inner_circles) < 1e-12);
+        let fixed = recentred.inner_circles[2].center.unwrap();
+        assert_eq!((fixed.x, fixed.y), (-4.0, 0.0));
+        assert!(recentred.container.size >= packing.container.size);
+        for circle in &recentred.inner_circles {
+            assert!(recentred.container.contains(circle));
+        }
+    }
+
+    #[test]
+    fn refined_packing_is_balanced() {
+        let radiuses: Vec<FloatType> = (1..=8).map(|radius| radius as FloatType).collect();
+        let packing = HeuristicAlgorithmBuilder::new()
+            .iterations(20)
+            .radiuses(radiuses)
+            .max_small_circle_radius(2.0)
+            .build()
+            .unwrap()
+            .find(None)
+            .packing
+            .unwrap();
+        let balance = Balance::new(0.1);
+        assert!(balance.imbalance(&packing.inner_circles) > balance.max_offset);
+        let radiuses = packing.inner_circles_radiuses();
+        let ralgo = RAlgorithmBuilder::new()
+            .objective(
+                CirclesPackingObjective::new(radiuses.clone())
+                    .with_balance(Some(balance.clone()))
+                    .unwrap(),
+            )
+            .build();
+
+        let x = recentre(&packing, &balance).into_coords_vec().unwrap();
+        let y = dichotomy_step_ralgo(x, false, 1e-3, ralgo).unwrap().x;
+        let refined = CirclesPacking::from_coords_vec_and_radiuses(y, &radiuses)
+            .unwrap()
+            .with_balance(Some(balance));
+
+        let report = refined.validate(1e-6);
+        assert!(report.is_balanced(), "{report}");
+        assert!(report.max_imbalance.is_some());
+        assert!(refined.validate(1e-3).is_valid());
+    }
+}
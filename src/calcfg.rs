@@ -1,84 +1,814 @@
-use nalgebra::DVector;
+use nalgebra::{DVector, DVectorView};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
-use crate::utils::FloatType;
+use crate::{
+    balance::Balance,
+    circle::Circle,
+    clearance::Clearance,
+    container::ContainerShape,
+    error::Error,
+    neighbour_list::{NeighbourCache, NeighbourList},
+    ralgo::Objective,
+    utils::{cast, check_radiuses, checked, to_float_type, Float, FloatType},
+};
 
-fn concat_gradients(
-    gx: &DVector<FloatType>,
-    gy: &DVector<FloatType>,
-    gr: FloatType,
-) -> DVector<FloatType> {
+/// Objectives with at least this many circles check only pairs from a neighbour list by default.
+pub const NEIGHBOUR_LIST_MIN_CIRCLES: usize = 64;
+
+/// Objectives with at least this many circles check pairs on the rayon thread pool by default.
+pub const PARALLEL_MIN_CIRCLES: usize = 2048;
+
+/// Penalty function of packing circles with the given radiuses into a container of the minimum size.
+///
+/// Variables are `(x_1, ..., x_n, y_1, ..., y_n, size)`, see [`crate::container::Container`].
+#[derive(Clone, Debug)]
+pub struct CirclesPackingObjective<T: Float = FloatType> {
+    radiuses: Vec<T>,
+    shape: ContainerShape<T>,
+    immovable: Immovable<T>,
+    clearance: Clearance<T>,
+    balance: Option<Balance<T>>,
+    neighbour_list: bool,
+    neighbours: NeighbourCache<T>,
+    parallel: bool,
+}
+
+impl<T: Float> CirclesPackingObjective<T> {
+    pub fn new(radiuses: Vec<T>) -> Self {
+        Self {
+            neighbour_list: radiuses.len() >= NEIGHBOUR_LIST_MIN_CIRCLES,
+            parallel: radiuses.len() >= PARALLEL_MIN_CIRCLES,
+            radiuses,
+            shape: ContainerShape::Circle,
+            immovable: Immovable::default(),
+            clearance: Clearance::default(),
+            balance: None,
+            neighbours: NeighbourCache::default(),
+        }
+    }
+
+    pub fn with_shape(mut self, shape: ContainerShape<T>) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// Circles marked as fixed keep their positions, the mask has a flag for every circle or
+    /// is empty.
+    pub fn with_fixed(mut self, fixed: Vec<bool>) -> Result<Self, Error> {
+        check_fixed(&fixed, self.radiuses.len())?;
+        self.immovable.fixed = fixed;
+        Ok(self)
+    }
+
+    /// Circular obstacles with centers, inner circles are penalised for intersecting them.
+    pub fn with_obstacles(mut self, obstacles: Vec<Circle<T>>) -> Self {
+        self.immovable.obstacles = obstacles;
+        self
+    }
+
+    pub fn with_clearance(mut self, clearance: Clearance<T>) -> Self {
+        self.clearance = clearance;
+        self
+    }
+
+    /// Centre of mass of circles is penalised for lying farther than the allowed offset from
+    /// (0, 0), masses default to circles areas.
+    pub fn with_balance(mut self, balance: Option<Balance<T>>) -> Result<Self, Error> {
+        self.balance = balance
+            .map(|balance| resolve_masses(balance, &self.radiuses))
+            .transpose()?;
+        Ok(self)
+    }
+
+    /// Check only pairs of nearby circles for overlap, results are the same as with checking
+    /// every pair. Enabled by default for [`NEIGHBOUR_LIST_MIN_CIRCLES`] and more circles.
+    pub fn with_neighbour_list(mut self, enabled: bool) -> Self {
+        self.neighbour_list = enabled;
+        self
+    }
+
+    /// Check pairs of circles on the rayon thread pool, results may differ from the serial
+    /// check in the last bits. Enabled by default for [`PARALLEL_MIN_CIRCLES`] and more circles.
+    pub fn with_parallel(mut self, enabled: bool) -> Self {
+        self.parallel = enabled;
+        self
+    }
+
+    pub fn radiuses(&self) -> &[T] {
+        &self.radiuses
+    }
+
+    pub fn shape(&self) -> ContainerShape<T> {
+        self.shape.clone()
+    }
+}
+
+impl<T: Float> Objective<T> for CirclesPackingObjective<T> {
+    fn value(&self, x: &DVector<T>) -> T {
+        self.calcfg(x).0
+    }
+
+    fn subgradient(&self, x: &DVector<T>) -> DVector<T> {
+        self.calcfg(x).1
+    }
+
+    fn dimension(&self) -> usize {
+        2 * self.radiuses.len() + 1
+    }
+
+    fn name(&self) -> &str {
+        "circles packing"
+    }
+
+    fn calcfg(&self, x: &DVector<T>) -> (T, DVector<T>) {
+        let neighbours = self.neighbour_list.then(|| {
+            self.neighbours
+                .get(x, &self.radiuses, self.clearance.pair.clone())
+        });
+        let neighbours = neighbours.as_ref().and_then(|list| list.as_ref());
+
+        let (f, g) = match &self.shape {
+            ContainerShape::Circle => circle_calcfg(
+                x,
+                &self.radiuses,
+                &self.clearance,
+                neighbours,
+                self.parallel,
+            ),
+            shape => box_calcfg(
+                x,
+                &self.radiuses,
+                shape.clone(),
+                &self.clearance,
+                neighbours,
+                self.parallel,
+            ),
+        };
+
+        let (f, g) = match &self.balance {
+            Some(balance) => balance_penalty(x, balance, f, g),
+            None => (f, g),
+        };
+
+        self.immovable
+            .apply(x, &self.radiuses, self.clearance.pair.clone(), f, g)
+    }
+
+    fn fixed_variables(&self) -> Vec<usize> {
+        self.immovable.fixed_variables(self.radiuses.len())
+    }
+}
+
+/// Fixed circles and obstacles of the packing objective.
+#[derive(Clone, Debug, Default)]
+struct Immovable<T: Float> {
+    fixed: Vec<bool>,
+    obstacles: Vec<Circle<T>>,
+}
+
+impl<T: Float> Immovable<T> {
+    /// Indexes of `x` and `y` variables of fixed circles among `n` circles.
+    fn fixed_variables(&self, n: usize) -> Vec<usize> {
+        let fixed = (0..n).filter(|&i| self.fixed.get(i).copied().unwrap_or(false));
+
+        fixed.clone().chain(fixed.map(|i| n + i)).collect()
+    }
+
+    /// Add obstacle penalties and zero subgradient of fixed variables.
+    fn apply(
+        &self,
+        x: &DVector<T>,
+        radiuses: &[T],
+        gap: T,
+        mut f: T,
+        mut g: DVector<T>,
+    ) -> (T, DVector<T>) {
+        let n = radiuses.len();
+        let (p1, eps) = (cast::<T>(P1), cast::<T>(EPS));
+
+        for i in 0..n {
+            for obstacle in &self.obstacles {
+                let Some(center) = &obstacle.center else {
+                    continue;
+                };
+
+                let cx_diff = x[i].clone() - center.x.clone();
+                let cy_diff = x[n + i].clone() - center.y.clone();
+                let radius_sum = radiuses[i].clone() + obstacle.radius.clone() + gap.clone();
+
+                let temp = -(cx_diff.clone() * cx_diff.clone() + cy_diff.clone() * cy_diff.clone())
+                    + radius_sum.clone() * radius_sum
+                    + eps.clone();
+                if temp > T::zero() {
+                    f += p1.clone() * temp;
+                    g[i] -= p1.clone() * cx_diff;
+                    g[n + i] -= p1.clone() * cy_diff;
+                }
+            }
+        }
+
+        for i in self.fixed_variables(n) {
+            g[i] = T::zero();
+        }
+
+        (f, g)
+    }
+}
+
+/// Check that the fixed mask is empty or has a flag for every one of `n` circles.
+fn check_fixed(fixed: &[bool], n: usize) -> Result<(), Error> {
+    if !fixed.is_empty() && fixed.len() != n {
+        return Err(Error::InvalidParameter {
+            name: "fixed",
+            message: format!(
+                "expected a flag for each of {n} circles, got {}",
+                fixed.len()
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+/// Balance with a mass for every circle, masses must be positive and the allowed offset must
+/// not be negative.
+fn resolve_masses<T: Float>(balance: Balance<T>, radiuses: &[T]) -> Result<Balance<T>, Error> {
+    if !(balance.max_offset >= T::zero() && balance.max_offset.is_finite()) {
+        return Err(Error::InvalidParameter {
+            name: "max_offset",
+            message: format!(
+                "must not be negative, got {}",
+                to_float_type(balance.max_offset.clone())
+            ),
+        });
+    }
+
+    let masses = balance.masses(radiuses);
+    if masses.len() != radiuses.len() {
+        return Err(Error::InvalidParameter {
+            name: "masses",
+            message: format!(
+                "expected a mass for each of {} circles, got {}",
+                radiuses.len(),
+                masses.len()
+            ),
+        });
+    }
+    if let Some(index) = masses
+        .iter()
+        .position(|mass| !(*mass > T::zero() && mass.is_finite()))
+    {
+        return Err(Error::InvalidParameter {
+            name: "masses",
+            message: format!(
+                "mass of circle {index} must be positive, got {}",
+                to_float_type(masses[index].clone())
+            ),
+        });
+    }
+
+    Ok(Balance { masses, ..balance })
+}
+
+/// Penalty of the centre of mass lying farther than the allowed offset from (0, 0), masses
+/// must be resolved.
+fn balance_penalty<T: Float>(
+    x: &DVector<T>,
+    balance: &Balance<T>,
+    mut f: T,
+    mut g: DVector<T>,
+) -> (T, DVector<T>) {
+    let n = balance.masses.len();
+    let total_mass = balance
+        .masses
+        .iter()
+        .fold(T::zero(), |sum, mass| sum + mass.clone());
+
+    let (mut center_x, mut center_y) = (T::zero(), T::zero());
+    for (i, mass) in balance.masses.iter().enumerate() {
+        center_x += mass.clone() * x[i].clone();
+        center_y += mass.clone() * x[n + i].clone();
+    }
+    let (center_x, center_y) = (center_x / total_mass.clone(), center_y / total_mass.clone());
+    let offset = center_x.clone().hypot(center_y.clone());
+
+    let p1: T = cast(P1);
+    let temp = offset.clone() - balance.max_offset.clone();
+    if temp > T::zero() {
+        f += p1.clone() * temp;
+
+        for (i, mass) in balance.masses.iter().enumerate() {
+            let share = p1.clone() * mass.clone() / (total_mass.clone() * offset.clone());
+            g[i] += share.clone() * center_x.clone();
+            g[n + i] += share * center_y.clone();
+        }
+    }
+
+    (f, g)
+}
+
+const P1: FloatType = 2000.0;
+const P2: FloatType = 1000.0;
+const EPS: FloatType = 1e-24;
+
+/// Overlap penalty of circle `i` with every circle `j > i` closer than `gap` to it.
+///
+/// Only neighbours of circle `i` are checked if the list is given, they are visited in the same
+/// order, so the sums are the same as with checking every circle.
+#[allow(clippy::too_many_arguments)]
+fn pair_penalties<T: Float>(
+    i: usize,
+    cx: &DVectorView<T>,
+    cy: &DVectorView<T>,
+    radiuses: &[T],
+    gap: T,
+    neighbours: Option<&NeighbourList<T>>,
+    f: &mut T,
+    gx: &mut DVector<T>,
+    gy: &mut DVector<T>,
+) {
+    let (p1, eps) = (cast::<T>(P1), cast::<T>(EPS));
+    let mut penalty = |j: usize| {
+        let cx_diff = cx[i].clone() - cx[j].clone();
+        let cy_diff = cy[i].clone() - cy[j].clone();
+        let radius_sum = radiuses[i].clone() + radiuses[j].clone() + gap.clone();
+
+        let temp = -(cx_diff.clone() * cx_diff.clone() + cy_diff.clone() * cy_diff.clone())
+            + radius_sum.clone() * radius_sum
+            + eps.clone();
+        if temp > T::zero() {
+            *f += p1.clone() * temp;
+            let scaled_cx = p1.clone() * cx_diff;
+            let scaled_cy = p1.clone() * cy_diff;
+
+            gx[i] -= scaled_cx.clone();
+            gy[i] -= scaled_cy.clone();
+            gx[j] += scaled_cx;
+            gy[j] += scaled_cy;
+        }
+    };
+
+    match neighbours {
+        Some(list) => list.neighbours(i).iter().for_each(|&j| penalty(j)),
+        None => ((i + 1)..radiuses.len()).for_each(penalty),
+    }
+}
+
+/// Overlap penalties of every pair of circles, split between the threads of the rayon pool.
+///
+/// Thread `k` of `m` checks circles `i = k, k + m, ...` against circles `j > i` like the serial
+/// loop and sums the subgradient into its own buffers, which are added up in order. Results are
+/// reproducible for the same number of threads, but the sums are taken in a different order
+/// than in the serial loop and may differ from it in the last bits.
+fn parallel_pair_penalties<T: Float>(
+    cx: &DVectorView<T>,
+    cy: &DVectorView<T>,
+    radiuses: &[T],
+    gap: T,
+    neighbours: Option<&NeighbourList<T>>,
+) -> (T, DVector<T>, DVector<T>) {
+    let (n, threads) = (radiuses.len(), rayon::current_num_threads());
+
+    let accumulators: Vec<(T, DVector<T>, DVector<T>)> = (0..threads)
+        .into_par_iter()
+        .map(|thread| {
+            let (mut f, mut gx, mut gy) = (T::zero(), DVector::zeros(n), DVector::zeros(n));
+            for i in (thread..n).step_by(threads) {
+                pair_penalties(
+                    i,
+                    cx,
+                    cy,
+                    radiuses,
+                    gap.clone(),
+                    neighbours,
+                    &mut f,
+                    &mut gx,
+                    &mut gy,
+                );
+            }
+            (f, gx, gy)
+        })
+        .collect();
+
+    accumulators.into_iter().fold(
+        (T::zero(), DVector::zeros(n), DVector::zeros(n)),
+        |(f, gx, gy), (thread_f, thread_gx, thread_gy)| {
+            (f + thread_f, gx + thread_gx, gy + thread_gy)
+        },
+    )
+}
+
+/// Adds overlap penalties of every pair of circles computed in parallel.
+fn add_parallel_pair_penalties<T: Float>(
+    pairs: Option<(T, DVector<T>, DVector<T>)>,
+    f: &mut T,
+    gx: &mut DVector<T>,
+    gy: &mut DVector<T>,
+) {
+    if let Some((pair_f, pair_gx, pair_gy)) = pairs {
+        *f += pair_f;
+        *gx += pair_gx;
+        *gy += pair_gy;
+    }
+}
+
+fn concat_gradients<T: Float>(gx: &DVector<T>, gy: &DVector<T>, gr: T) -> DVector<T> {
     let circles_number = gx.len();
 
-    let mut gradient = DVector::<FloatType>::zeros(2 * circles_number + 1);
-    gradient.rows_mut(0, circles_number).copy_from(&gx);
+    let mut gradient = DVector::<T>::zeros(2 * circles_number + 1);
+    gradient.rows_mut(0, circles_number).copy_from(gx);
     gradient
         .rows_mut(circles_number, circles_number)
-        .copy_from(&gy);
+        .copy_from(gy);
     gradient[2 * circles_number] = gr;
 
-    return gradient;
+    gradient
+}
+
+/// Check radiuses and that there are `2n + 1` finite variables.
+fn check_variables<T: Float>(x: &DVector<T>, radiuses: &[T]) -> Result<(), Error> {
+    check_radiuses(radiuses)?;
+
+    let expected = 2 * radiuses.len() + 1;
+    if x.len() != expected {
+        return Err(Error::DimensionMismatch {
+            expected,
+            found: x.len(),
+        });
+    }
+
+    if x.iter().any(|value| !value.is_finite()) {
+        return Err(Error::NotANumber);
+    }
+
+    Ok(())
 }
 
-pub fn calcfg(
-    x: &DVector<FloatType>,
-    radiuses: &Vec<FloatType>,
-) -> (FloatType, DVector<FloatType>) {
+pub fn calcfg<T: Float>(x: &DVector<T>, radiuses: &[T]) -> Result<(T, DVector<T>), Error> {
+    calcfg_with_clearance(x, radiuses, &Clearance::default(), None, false)
+}
+
+/// Penalty function with clearances, every pair of circles is checked for overlap unless the
+/// neighbour list is given. Pairs are checked on the rayon thread pool if `parallel` is set.
+pub fn calcfg_with_clearance<T: Float>(
+    x: &DVector<T>,
+    radiuses: &[T],
+    clearance: &Clearance<T>,
+    neighbours: Option<&NeighbourList<T>>,
+    parallel: bool,
+) -> Result<(T, DVector<T>), Error> {
+    check_variables(x, radiuses)?;
+    checked(circle_calcfg(x, radiuses, clearance, neighbours, parallel))
+}
+
+fn circle_calcfg<T: Float>(
+    x: &DVector<T>,
+    radiuses: &[T],
+    clearance: &Clearance<T>,
+    neighbours: Option<&NeighbourList<T>>,
+    parallel: bool,
+) -> (T, DVector<T>) {
     let number_of_circles = radiuses.len();
 
     let cx = x.rows(0, number_of_circles);
     let cy = x.rows(number_of_circles, number_of_circles);
-    let main_circle_radius = x[x.len() - 1];
+    let main_circle_radius = x[x.len() - 1].clone();
+
+    let mut gx = DVector::<T>::zeros(number_of_circles);
+    let mut gy = DVector::<T>::zeros(number_of_circles);
+    let mut gr = T::one();
+    let (p1, p2, eps) = (cast::<T>(P1), cast::<T>(P2), cast::<T>(EPS));
 
-    let mut gx = DVector::<FloatType>::zeros(number_of_circles);
-    let mut gy = DVector::<FloatType>::zeros(number_of_circles);
-    let mut gr = 1 as FloatType;
+    let mut f = main_circle_radius.clone();
 
-    let mut f = main_circle_radius;
-    const P1: FloatType = 2000.0;
-    const P2: FloatType = 1000.0;
-    const EPS: FloatType = 1e-24;
+    let pairs = parallel
+        .then(|| parallel_pair_penalties(&cx, &cy, radiuses, clearance.pair.clone(), neighbours));
 
     for i in 0..number_of_circles {
-        let (cx_i, cy_i) = (cx[i], cy[i]);
-        let radius_diff = main_circle_radius - radiuses[i];
-
-        let mut temp = cx_i * cx_i + cy_i * cy_i - radius_diff * radius_diff + EPS;
-        if temp > 0.0 {
-            f += P1 * temp;
-            gx[i] += P1 * cx_i;
-            gy[i] += P1 * cy_i;
-            gr -= P2;
+        let (cx_i, cy_i) = (cx[i].clone(), cy[i].clone());
+        let radius_diff =
+            main_circle_radius.clone() - (radiuses[i].clone() + clearance.wall.clone());
+
+        let temp = cx_i.clone() * cx_i.clone() + cy_i.clone() * cy_i.clone()
+            - radius_diff.clone() * radius_diff
+            + eps.clone();
+        if temp > T::zero() {
+            f += p1.clone() * temp;
+            gx[i] += p1.clone() * cx_i;
+            gy[i] += p1.clone() * cy_i;
+            gr -= p2.clone();
         }
 
-        for j in (i + 1)..number_of_circles {
-            let cx_diff = cx_i - cx[j];
-            let cy_diff = cy_i - cy[j];
-            let radius_sum = radiuses[i] + radiuses[j];
-
-            temp = -(cx_diff * cx_diff + cy_diff * cy_diff) + radius_sum * radius_sum + EPS;
-            if temp > 0.0 {
-                f += P1 * temp;
-                let scaled_cx = P1 * cx_diff;
-                let scaled_cy = P1 * cy_diff;
-
-                gx[i] -= scaled_cx;
-                gy[i] -= scaled_cy;
-                gx[j] += scaled_cx;
-                gy[j] += scaled_cy;
-            }
+        if !parallel {
+            pair_penalties(
+                i,
+                &cx,
+                &cy,
+                radiuses,
+                clearance.pair.clone(),
+                neighbours,
+                &mut f,
+                &mut gx,
+                &mut gy,
+            );
         }
     }
+    add_parallel_pair_penalties(pairs, &mut f, &mut gx, &mut gy);
 
     let temp = -main_circle_radius
         + radiuses
             .iter()
-            .min_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap();
+            .cloned()
+            .fold(cast(FloatType::INFINITY), T::min)
+        + clearance.wall.clone();
 
-    if temp > 0.0 {
-        f += P2 * temp;
-        gr -= P2;
+    if temp > T::zero() {
+        f += p2.clone() * temp;
+        gr -= p2;
     }
 
     (f, concat_gradients(&gx, &gy, gr))
 }
+
+/// Penalty function for square, rectangle and strip containers, the last variable is the side
+/// of a square, the height of a rectangle or the length of a strip.
+pub fn calcfg_box<T: Float>(
+    x: &DVector<T>,
+    radiuses: &[T],
+    shape: ContainerShape<T>,
+) -> Result<(T, DVector<T>), Error> {
+    calcfg_box_with_clearance(x, radiuses, shape, &Clearance::default(), None, false)
+}
+
+pub fn calcfg_box_with_clearance<T: Float>(
+    x: &DVector<T>,
+    radiuses: &[T],
+    shape: ContainerShape<T>,
+    clearance: &Clearance<T>,
+    neighbours: Option<&NeighbourList<T>>,
+    parallel: bool,
+) -> Result<(T, DVector<T>), Error> {
+    check_variables(x, radiuses)?;
+    checked(box_calcfg(
+        x, radiuses, shape, clearance, neighbours, parallel,
+    ))
+}
+
+fn box_calcfg<T: Float>(
+    x: &DVector<T>,
+    radiuses: &[T],
+    shape: ContainerShape<T>,
+    clearance: &Clearance<T>,
+    neighbours: Option<&NeighbourList<T>>,
+    parallel: bool,
+) -> (T, DVector<T>) {
+    let number_of_circles = radiuses.len();
+
+    let cx = x.rows(0, number_of_circles);
+    let cy = x.rows(number_of_circles, number_of_circles);
+    let size = x[x.len() - 1].clone();
+
+    // Half extents with their derivatives by the size, fixed sides do not depend on it.
+    let (two, half) = (cast::<T>(2.0), cast::<T>(0.5));
+    let ((half_width, width_gradient), (half_height, height_gradient)) = match shape {
+        ContainerShape::Rectangle { width } => {
+            ((width / two.clone(), T::zero()), (size.clone() / two, half))
+        }
+        ContainerShape::Strip { width } => {
+            ((size.clone() / two.clone(), half), (width / two, T::zero()))
+        }
+        _ => (
+            (size.clone() / two.clone(), half.clone()),
+            (size.clone() / two, half),
+        ),
+    };
+
+    let mut gx = DVector::<T>::zeros(number_of_circles);
+    let mut gy = DVector::<T>::zeros(number_of_circles);
+    let mut gs = T::one();
+    let (p1, p2, eps) = (cast::<T>(P1), cast::<T>(P2), cast::<T>(EPS));
+
+    let mut f = size.clone();
+
+    let pairs = parallel
+        .then(|| parallel_pair_penalties(&cx, &cy, radiuses, clearance.pair.clone(), neighbours));
+
+    for i in 0..number_of_circles {
+        let (cx_i, cy_i) = (cx[i].clone(), cy[i].clone());
+
+        let temp = cx_i.clone().abs() + radiuses[i].clone() + clearance.wall.clone()
+            - half_width.clone()
+            + eps.clone();
+        if temp > T::zero() {
+            f += p1.clone() * temp;
+            gx[i] += p1.clone() * cx_i.signum();
+            gs -= p1.clone() * width_gradient.clone();
+        }
+
+        let temp = cy_i.clone().abs() + radiuses[i].clone() + clearance.wall.clone()
+            - half_height.clone()
+            + eps.clone();
+        if temp > T::zero() {
+            f += p1.clone() * temp;
+            gy[i] += p1.clone() * cy_i.signum();
+            gs -= p1.clone() * height_gradient.clone();
+        }
+
+        if !parallel {
+            pair_penalties(
+                i,
+                &cx,
+                &cy,
+                radiuses,
+                clearance.pair.clone(),
+                neighbours,
+                &mut f,
+                &mut gx,
+                &mut gy,
+            );
+        }
+    }
+    add_parallel_pair_penalties(pairs, &mut f, &mut gx, &mut gy);
+
+    let temp = -size
+        + cast::<T>(2.0)
+            * (radiuses
+                .iter()
+                .cloned()
+                .fold(cast(FloatType::NEG_INFINITY), T::max)
+                + clearance.wall.clone());
+
+    if temp > T::zero() {
+        f += p2.clone() * temp;
+        gs -= p2;
+    }
+
+    (f, concat_gradients(&gx, &gy, gs))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn fixed_circle_freezes_its_coordinates() {
+        let objective = CirclesPackingObjective::new(vec![1.0; 3])
+            .with_fixed(vec![true, false, false])
+            .unwrap();
+
+        assert_eq!(objective.fixed_variables(), vec![0, 3]);
+    }
+
+    #[test]
+    fn fixed_mask_of_wrong_length_is_rejected() {
+        let objective = CirclesPackingObjective::new(vec![1.0; 3]);
+
+        assert!(objective.clone().with_fixed(vec![true]).is_err());
+        assert!(objective.with_fixed(vec![false; 4]).is_err());
+    }
+
+    #[test]
+    fn invalid_balance_is_rejected() {
+        let objective = CirclesPackingObjective::new(vec![1.0; 3]);
+
+        assert!(objective
+            .clone()
+            .with_balance(Some(Balance::new(0.5).with_masses(vec![1.0; 2])))
+            .is_err());
+        assert!(objective.with_balance(Some(Balance::new(-1.0))).is_err());
+    }
+
+    #[test]
+    fn balanced_centre_of_mass_has_finite_penalty() {
+        let objective = CirclesPackingObjective::<FloatType>::new(vec![1.0, 1.0])
+            .with_balance(Some(Balance::new(0.0)))
+            .unwrap();
+        let x = DVector::from_vec(vec![-1.5, 1.5, 0.0, 0.0, 3.0]);
+
+        let (f, g) = objective.calcfg(&x);
+
+        assert!(f.is_finite());
+        assert!(g.iter().all(|value| value.is_finite()));
+    }
+
+    #[test]
+    fn circles_touching_at_pair_clearance_are_not_penalised() {
+        let radiuses: [FloatType; 2] = [1.0, 1.5];
+        let clearance = Clearance::new(0.5, 0.25);
+        // Gap between the circles is `0.5 - shift`, both stay well inside the container.
+        let penalty = |shift: FloatType| {
+            let x = DVector::from_vec(vec![-1.5 + shift, 1.5, 0.0, 0.0, 10.0]);
+            calcfg_with_clearance(&x, &radiuses, &clearance, None, false)
+                .unwrap()
+                .0
+                - 10.0
+        };
+
+        assert_eq!(penalty(0.0), 0.0);
+        assert_eq!(penalty(-0.1), 0.0);
+        assert!(penalty(0.01) > 0.0);
+    }
+
+    #[test]
+    fn balance_subgradient_matches_finite_differences() {
+        let objective = CirclesPackingObjective::<FloatType>::new(vec![1.0, 1.5, 2.0])
+            .with_balance(Some(Balance::new(0.2).with_masses(vec![1.0, 2.0, 3.0])))
+            .unwrap();
+        // Circles do not overlap and stay inside the container, only the balance is violated.
+        let x = DVector::from_vec(vec![-2.0, 1.5, 3.2, 0.5, 4.1, -1.3, 12.0]);
+        let (_, g) = objective.calcfg(&x);
+
+        let h = 1e-6;
+        for i in 0..x.len() {
+            let (mut forward, mut backward) = (x.clone(), x.clone());
+            forward[i] += h;
+            backward[i] -= h;
+            let difference = (objective.value(&forward) - objective.value(&backward)) / (2.0 * h);
+
+            assert!(
+                (difference - g[i]).abs() <= 1e-4 * g[i].abs().max(1.0),
+                "variable {i}: {difference} vs {}",
+                g[i]
+            );
+        }
+        assert!(g.rows(0, 6).norm() > 0.0);
+    }
+
+    #[test]
+    fn box_subgradient_matches_finite_differences() {
+        let radiuses: [FloatType; 3] = [1.0, 1.5, 2.0];
+        // Circles stick out of the walls without overlapping each other: pair penalties share
+        // the half-scaled subgradient of the original circle objective. The smaller size is
+        // also below the biggest diameter. No penalty is on its kink.
+        let centers = [-3.2, 0.1, 2.9, 0.4, -2.1, 1.8];
+        let shapes = [
+            ContainerShape::Square,
+            ContainerShape::Rectangle { width: 6.1 },
+            ContainerShape::Strip { width: 6.1 },
+        ];
+
+        for (shape, size) in shapes
+            .into_iter()
+            .flat_map(|shape| [(shape, 5.3), (shape, 3.9)])
+        {
+            let x = DVector::from_iterator(7, centers.into_iter().chain([size]));
+            let (_, g) = calcfg_box(&x, &radiuses, shape).unwrap();
+
+            let h = 1e-6;
+            for i in 0..x.len() {
+                let (mut forward, mut backward) = (x.clone(), x.clone());
+                forward[i] += h;
+                backward[i] -= h;
+                let difference = (calcfg_box(&forward, &radiuses, shape).unwrap().0
+                    - calcfg_box(&backward, &radiuses, shape).unwrap().0)
+                    / (2.0 * h);
+
+                assert!(
+                    (difference - g[i]).abs() <= 1e-4 * g[i].abs().max(1.0),
+                    "{shape:?} of size {size}, variable {i}: {difference} vs {}",
+                    g[i]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_penalty_is_reproducible_and_close_to_serial() {
+        let n = 500;
+        let mut rng = StdRng::seed_from_u64(3);
+        let radiuses: Vec<FloatType> = (0..n).map(|_| rng.gen_range(1.0..3.0)).collect();
+        let x = DVector::from_iterator(
+            2 * n + 1,
+            (0..2 * n).map(|_| rng.gen_range(-40.0..40.0)).chain([60.0]),
+        );
+        let clearance = Clearance::default();
+        let list = NeighbourList::new(&x, &radiuses, clearance.pair, 1.0);
+        let (serial_f, serial_g) =
+            calcfg_with_clearance(&x, &radiuses, &clearance, None, false).unwrap();
+
+        for threads in [1, 2, 3, 8] {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            let parallel = |neighbours| {
+                pool.install(|| calcfg_with_clearance(&x, &radiuses, &clearance, neighbours, true))
+                    .unwrap()
+            };
+
+            let (f, g) = parallel(None);
+            assert_eq!(parallel(None), (f, g.clone()));
+            assert_eq!(parallel(Some(&list)), (f, g.clone()));
+            assert!((f - serial_f).abs() <= 1e-9 * serial_f.abs());
+            assert!((&g - &serial_g).norm() <= 1e-9 * serial_g.norm());
+        }
+    }
+
+    #[test]
+    fn big_objectives_check_pairs_in_parallel() {
+        assert!(!CirclesPackingObjective::new(vec![1.0; PARALLEL_MIN_CIRCLES - 1]).parallel);
+        assert!(CirclesPackingObjective::new(vec![1.0; PARALLEL_MIN_CIRCLES]).parallel);
+    }
+}
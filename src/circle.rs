@@ -1,32 +1,35 @@
-use crate::{point::Point, utils::FloatType};
+use crate::{
+    point::Point,
+    utils::{cast, Float, FloatType},
+};
 
 #[derive(Clone, Copy, Debug, Default)]
-pub struct Circle {
-    pub center: Option<Point>,
-    pub radius: FloatType,
+pub struct Circle<T: Float = FloatType> {
+    pub center: Option<Point<T>>,
+    pub radius: T,
 }
 
-impl Circle {
-    pub fn new(radius: FloatType, center: Point) -> Self {
+impl<T: Float> Circle<T> {
+    pub fn new(radius: T, center: Point<T>) -> Self {
         Circle {
             center: Some(center),
             radius,
         }
     }
 
-    pub fn with_radius(radius: FloatType) -> Self {
+    pub fn with_radius(radius: T) -> Self {
         Circle {
             center: None,
             radius,
         }
     }
 
-    pub fn overlap(&self, other: &Circle) -> bool {
-        if let (Some(center_self), Some(center_other)) = (self.center, other.center) {
-            let distance = ((center_self.x - center_other.x).powi(2)
-                + (center_self.y - center_other.y).powi(2))
+    pub fn overlap(&self, other: &Circle<T>) -> bool {
+        if let (Some(center_self), Some(center_other)) = (&self.center, &other.center) {
+            let distance = ((center_self.x.clone() - center_other.x.clone()).powi(2)
+                + (center_self.y.clone() - center_other.y.clone()).powi(2))
             .sqrt();
-            let radius_sum = self.radius + other.radius;
+            let radius_sum = self.radius.clone() + other.radius.clone();
 
             distance <= radius_sum
         } else {
@@ -34,11 +37,25 @@ impl Circle {
         }
     }
 
-    pub fn overlap_quad(&self, other: &Circle) -> bool {
-        if let (Some(center_self), Some(center_other)) = (self.center, other.center) {
-            let distance =
-                (center_self.x - center_other.x).powi(2) + (center_self.y - center_other.y).powi(2);
-            let radius_sum = (self.radius + other.radius).powi(2);
+    /// Whether circles are closer to each other than `clearance`.
+    pub fn overlap_with_clearance(&self, other: &Circle<T>, clearance: T) -> bool {
+        if let (Some(center_self), Some(center_other)) = (&self.center, &other.center) {
+            let distance = ((center_self.x.clone() - center_other.x.clone()).powi(2)
+                + (center_self.y.clone() - center_other.y.clone()).powi(2))
+            .sqrt();
+            let radius_sum = self.radius.clone() + other.radius.clone() + clearance;
+
+            distance <= radius_sum
+        } else {
+            false
+        }
+    }
+
+    pub fn overlap_quad(&self, other: &Circle<T>) -> bool {
+        if let (Some(center_self), Some(center_other)) = (&self.center, &other.center) {
+            let distance = (center_self.x.clone() - center_other.x.clone()).powi(2)
+                + (center_self.y.clone() - center_other.y.clone()).powi(2);
+            let radius_sum = (self.radius.clone() + other.radius.clone()).powi(2);
 
             distance <= radius_sum
         } else {
@@ -46,50 +63,55 @@ impl Circle {
         }
     }
 
-    pub fn is_overlap(&self, circles: &[Circle]) -> bool {
+    pub fn is_overlap(&self, circles: &[Circle<T>]) -> bool {
         circles.iter().any(|c| self.overlap(c))
     }
 
-    pub fn is_overlap_quad(&self, circles: &[Circle]) -> bool {
-        circles.iter().any(|c| self.overlap_quad(c))
+    pub fn is_overlap_with_clearance(&self, circles: &[Circle<T>], clearance: T) -> bool {
+        circles
+            .iter()
+            .any(|c| self.overlap_with_clearance(c, clearance.clone()))
     }
 
-    pub fn distance(&self, other: &Circle) -> FloatType {
-        if let (Some(center_self), Some(center_other)) = (self.center, other.center) {
-            let distance = ((center_self.x - center_other.x).powi(2)
-                + (center_self.y - center_other.y).powi(2))
-            .sqrt();
+    pub fn is_overlap_quad(&self, circles: &[Circle<T>]) -> bool {
+        circles.iter().any(|c| self.overlap_quad(c))
+    }
 
-            distance
+    pub fn distance(&self, other: &Circle<T>) -> T {
+        if let (Some(center_self), Some(center_other)) = (&self.center, &other.center) {
+            ((center_self.x.clone() - center_other.x.clone()).powi(2)
+                + (center_self.y.clone() - center_other.y.clone()).powi(2))
+            .sqrt()
         } else {
-            FloatType::INFINITY
+            cast(FloatType::INFINITY)
         }
     }
 
-    pub fn inside(&self, other: &Circle) -> bool {
-        if let (Some(center_self), Some(center_other)) = (self.center, other.center) {
-            let distance = ((center_self.x - center_other.x).powi(2)
-                + (center_self.y - center_other.y).powi(2))
+    pub fn inside(&self, other: &Circle<T>) -> bool {
+        if let (Some(center_self), Some(center_other)) = (&self.center, &other.center) {
+            let distance = ((center_self.x.clone() - center_other.x.clone()).powi(2)
+                + (center_self.y.clone() - center_other.y.clone()).powi(2))
             .sqrt();
 
-            distance <= (other.radius - self.radius)
+            distance <= (other.radius.clone() - self.radius.clone())
         } else {
             false
         }
     }
 
-    pub fn is_inside_main_circle(&self, main_circle_radius: FloatType) -> bool {
-        if let Some(center) = self.center {
-            let distance = (center.x.powi(2) + center.y.powi(2)).sqrt();
-            distance <= (main_circle_radius - self.radius)
+    pub fn is_inside_main_circle(&self, main_circle_radius: T) -> bool {
+        if let Some(center) = &self.center {
+            let distance = (center.x.clone().powi(2) + center.y.clone().powi(2)).sqrt();
+            distance <= (main_circle_radius - self.radius.clone())
         } else {
             false
         }
     }
 
-    pub fn is_inside_main_circle_quad(&self, main_circle_radius: FloatType) -> bool {
-        if let Some(center) = self.center {
-            center.x.powi(2) + center.y.powi(2) <= (main_circle_radius - self.radius).powi(2)
+    pub fn is_inside_main_circle_quad(&self, main_circle_radius: T) -> bool {
+        if let Some(center) = &self.center {
+            center.x.clone().powi(2) + center.y.clone().powi(2)
+                <= (main_circle_radius - self.radius.clone()).powi(2)
         } else {
             false
         }
@@ -0,0 +1,671 @@
+use std::{collections::HashSet, fmt, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    balance::Balance,
+    circle::Circle,
+    clearance::Clearance,
+    container::{Container, ContainerShape},
+    point::Point,
+    utils::FloatType,
+};
+
+use super::CirclesPacking;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackingFormat {
+    Json,
+    /// `index,radius,x,y` rows, the container is stored in the `main`, `square`, `rectangle` or
+    /// `strip` row with its size in the radius column and the fixed width in the x column.
+    /// Fixed circles have an extra `fixed` cell, obstacles are stored in `obstacle` rows and
+    /// the `clearance` row holds the pair clearance in the radius column and the wall one in x.
+    /// The `balance` row holds the allowed offset of the centre of mass, it is followed by a
+    /// `mass` row with the mass in the radius column for every circle if masses are custom.
+    Csv,
+}
+
+impl PackingFormat {
+    /// Guess format by file extension, json is used for unknown extensions.
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("csv") => PackingFormat::Csv,
+            _ => PackingFormat::Json,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PackingIoError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Parse {
+        line: usize,
+        message: String,
+    },
+    MissingCenter {
+        index: usize,
+    },
+    MissingMainCircle,
+    InvalidIndexes,
+    /// Number of original indexes differs from the number of circles.
+    IndexesMismatch {
+        circles: usize,
+        indexes: usize,
+    },
+    /// Fixed mask is not empty and its length differs from the number of circles.
+    FixedMismatch {
+        circles: usize,
+        flags: usize,
+    },
+    /// Balance masses are not empty and their number differs from the number of circles.
+    MassesMismatch {
+        circles: usize,
+        masses: usize,
+    },
+}
+
+impl fmt::Display for PackingIoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackingIoError::Io(err) => write!(f, "{err}"),
+            PackingIoError::Json(err) => write!(f, "invalid json packing: {err}"),
+            PackingIoError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            PackingIoError::MissingCenter { index } => {
+                write!(f, "circle with index {index} has no center")
+            }
+            PackingIoError::MissingMainCircle => {
+                write!(
+                    f,
+                    "missing `{CSV_MAIN_CIRCLE}`, `{CSV_SQUARE}`, `{CSV_RECTANGLE}` or `{CSV_STRIP}` row with the container size"
+                )
+            }
+            PackingIoError::InvalidIndexes => {
+                write!(f, "circle indexes are not unique")
+            }
+            PackingIoError::IndexesMismatch { circles, indexes } => {
+                write!(f, "{indexes} original indexes for {circles} circles")
+            }
+            PackingIoError::FixedMismatch { circles, flags } => {
+                write!(f, "{flags} fixed flags for {circles} circles")
+            }
+            PackingIoError::MassesMismatch { circles, masses } => {
+                write!(f, "{masses} balance masses for {circles} circles")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PackingIoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PackingIoError::Io(err) => Some(err),
+            PackingIoError::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PackingIoError {
+    fn from(err: io::Error) -> Self {
+        PackingIoError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for PackingIoError {
+    fn from(err: serde_json::Error) -> Self {
+        PackingIoError::Json(err)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PackingRecord {
+    #[serde(default)]
+    container: ContainerShape,
+    size: FloatType,
+    circles: Vec<CircleRecord>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    obstacles: Vec<ObstacleRecord>,
+    #[serde(default, skip_serializing_if = "Clearance::is_zero")]
+    clearance: Clearance,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    balance: Option<Balance>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CircleRecord {
+    index: usize,
+    radius: FloatType,
+    #[serde(default)]
+    center: Option<PointRecord>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    fixed: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ObstacleRecord {
+    radius: FloatType,
+    center: PointRecord,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PointRecord {
+    x: FloatType,
+    y: FloatType,
+}
+
+const CSV_HEADER: &str = "index,radius,x,y";
+const CSV_MAIN_CIRCLE: &str = "main";
+const CSV_SQUARE: &str = "square";
+const CSV_RECTANGLE: &str = "rectangle";
+const CSV_STRIP: &str = "strip";
+const CSV_OBSTACLE: &str = "obstacle";
+const CSV_FIXED: &str = "fixed";
+const CSV_CLEARANCE: &str = "clearance";
+const CSV_BALANCE: &str = "balance";
+const CSV_MASS: &str = "mass";
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl CirclesPacking {
+    pub fn save(&self, path: &Path) -> Result<(), PackingIoError> {
+        let content = match PackingFormat::from_path(path) {
+            PackingFormat::Json => self.to_json()?,
+            PackingFormat::Csv => self.to_csv()?,
+        };
+
+        fs::write(path, content)?;
+
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, PackingIoError> {
+        let content = fs::read_to_string(path)?;
+
+        match PackingFormat::from_path(path) {
+            PackingFormat::Json => Self::from_json(&content),
+            PackingFormat::Csv => Self::from_csv(&content),
+        }
+    }
+
+    /// Every circle must have a center, the same as for loading.
+    pub fn to_json(&self) -> Result<String, PackingIoError> {
+        self.check_lengths()?;
+        self.check_centers()?;
+
+        let record = PackingRecord {
+            container: self.container.shape,
+            size: self.container.size,
+            circles: self
+                .inner_circles
+                .iter()
+                .zip(&self.original_indexes)
+                .enumerate()
+                .map(|(i, (circle, &index))| CircleRecord {
+                    index,
+                    radius: circle.radius,
+                    center: circle.center.map(|center| PointRecord {
+                        x: center.x,
+                        y: center.y,
+                    }),
+                    fixed: self.is_fixed(i),
+                })
+                .collect(),
+            obstacles: self
+                .obstacles
+                .iter()
+                .filter_map(|obstacle| {
+                    let center = obstacle.center?;
+                    Some(ObstacleRecord {
+                        radius: obstacle.radius,
+                        center: PointRecord {
+                            x: center.x,
+                            y: center.y,
+                        },
+                    })
+                })
+                .collect(),
+            clearance: self.clearance,
+            balance: self.balance.clone(),
+        };
+
+        Ok(serde_json::to_string_pretty(&record)?)
+    }
+
+    /// Parse packing saved by [`CirclesPacking::to_json`], every circle must have a center.
+    pub fn from_json(content: &str) -> Result<Self, PackingIoError> {
+        let record: PackingRecord = serde_json::from_str(content)?;
+
+        let mut inner_circles = Vec::with_capacity(record.circles.len());
+        let mut original_indexes = Vec::with_capacity(record.circles.len());
+        let fixed = record.circles.iter().map(|circle| circle.fixed).collect();
+        let obstacles = record
+            .obstacles
+            .iter()
+            .map(|obstacle| {
+                Circle::new(
+                    obstacle.radius,
+                    Point {
+                        x: obstacle.center.x,
+                        y: obstacle.center.y,
+                    },
+                )
+            })
+            .collect();
+
+        for circle in record.circles {
+            let center = circle.center.ok_or(PackingIoError::MissingCenter {
+                index: circle.index,
+            })?;
+
+            inner_circles.push(Circle::new(
+                circle.radius,
+                Point {
+                    x: center.x,
+                    y: center.y,
+                },
+            ));
+            original_indexes.push(circle.index);
+        }
+
+        let packing = Self::from_records(
+            Container::new(record.container, record.size),
+            inner_circles,
+            original_indexes,
+        )?
+        .with_fixed(fixed)
+        .with_obstacles(obstacles)
+        .with_clearance(record.clearance)
+        .with_balance(record.balance);
+        packing.check_lengths()?;
+
+        Ok(packing)
+    }
+
+    /// Every circle must have a center, the same as for loading.
+    pub fn to_csv(&self) -> Result<String, PackingIoError> {
+        self.check_lengths()?;
+        self.check_centers()?;
+
+        let mut content = format!("{CSV_HEADER}\n");
+        content += &match self.container.shape {
+            ContainerShape::Circle => format!("{CSV_MAIN_CIRCLE},{},0,0\n", self.container.size),
+            ContainerShape::Square => format!("{CSV_SQUARE},{},0,0\n", self.container.size),
+            ContainerShape::Rectangle { width } => {
+                format!("{CSV_RECTANGLE},{},{width},0\n", self.container.size)
+            }
+            ContainerShape::Strip { width } => {
+                format!("{CSV_STRIP},{},{width},0\n", self.container.size)
+            }
+        };
+        if !self.clearance.is_zero() {
+            content += &format!(
+                "{CSV_CLEARANCE},{},{},0\n",
+                self.clearance.pair, self.clearance.wall
+            );
+        }
+        if let Some(balance) = &self.balance {
+            content += &format!("{CSV_BALANCE},{},0,0\n", balance.max_offset);
+            for mass in &balance.masses {
+                content += &format!("{CSV_MASS},{mass},0,0\n");
+            }
+        }
+
+        for (i, (circle, index)) in self
+            .inner_circles
+            .iter()
+            .zip(&self.original_indexes)
+            .enumerate()
+        {
+            if let Some(center) = circle.center {
+                content += &format!("{index},{},{},{}", circle.radius, center.x, center.y);
+            }
+            if self.is_fixed(i) {
+                content += &format!(",{CSV_FIXED}");
+            }
+            content += "\n";
+        }
+
+        for obstacle in &self.obstacles {
+            if let Some(center) = obstacle.center {
+                content += &format!(
+                    "{CSV_OBSTACLE},{},{},{}\n",
+                    obstacle.radius, center.x, center.y
+                );
+            }
+        }
+
+        Ok(content)
+    }
+
+    /// Parse packing saved by [`CirclesPacking::to_csv`], every circle must have a center.
+    pub fn from_csv(content: &str) -> Result<Self, PackingIoError> {
+        let mut container = None;
+        let mut inner_circles = Vec::new();
+        let mut original_indexes = Vec::new();
+        let mut fixed = Vec::new();
+        let mut obstacles = Vec::new();
+        let mut clearance = Clearance::default();
+        let mut balance = None;
+
+        let lines = content
+            .lines()
+            .enumerate()
+            .map(|(index, text)| (index + 1, text.trim()))
+            .filter(|(_, text)| !text.is_empty() && *text != CSV_HEADER);
+
+        for (line, text) in lines {
+            let cells: Vec<&str> = text.split(',').map(str::trim).collect();
+            let (index, radius, x, y, is_fixed) = match cells[..] {
+                [index, radius, x, y] => (index, radius, x, y, false),
+                [index, radius, x, y, CSV_FIXED] => (index, radius, x, y, true),
+                _ => {
+                    return Err(PackingIoError::Parse {
+                        line,
+                        message: format!("expected `{CSV_HEADER}[,{CSV_FIXED}]`, got `{text}`"),
+                    })
+                }
+            };
+
+            let radius = parse_cell::<FloatType>(radius, line)?;
+
+            match index {
+                CSV_MAIN_CIRCLE => {
+                    container = Some(Container::circle(radius));
+                    continue;
+                }
+                CSV_SQUARE => {
+                    container = Some(Container::square(radius));
+                    continue;
+                }
+                CSV_RECTANGLE => {
+                    container = Some(Container::rectangle(parse_cell(x, line)?, radius));
+                    continue;
+                }
+                CSV_STRIP => {
+                    container = Some(Container::strip(parse_cell(x, line)?, radius));
+                    continue;
+                }
+                CSV_OBSTACLE => {
+                    obstacles.push(Circle::new(
+                        radius,
+                        Point {
+                            x: parse_cell(x, line)?,
+                            y: parse_cell(y, line)?,
+                        },
+                    ));
+                    continue;
+                }
+                CSV_BALANCE => {
+                    balance = Some(Balance::new(radius));
+                    continue;
+                }
+                CSV_MASS => {
+                    let Some(balance) = &mut balance else {
+                        return Err(PackingIoError::Parse {
+                            line,
+                            message: format!("`{CSV_MASS}` row before the `{CSV_BALANCE}` row"),
+                        });
+                    };
+                    balance.masses.push(radius);
+                    continue;
+                }
+                CSV_CLEARANCE => {
+                    clearance = Clearance::new(radius, parse_cell(x, line)?);
+                    continue;
+                }
+                _ => {}
+            }
+
+            let index = parse_cell::<usize>(index, line)?;
+            if x.is_empty() || y.is_empty() {
+                return Err(PackingIoError::MissingCenter { index });
+            }
+
+            inner_circles.push(Circle::new(
+                radius,
+                Point {
+                    x: parse_cell(x, line)?,
+                    y: parse_cell(y, line)?,
+                },
+            ));
+            original_indexes.push(index);
+            fixed.push(is_fixed);
+        }
+
+        let container = container.ok_or(PackingIoError::MissingMainCircle)?;
+
+        let packing = Self::from_records(container, inner_circles, original_indexes)?
+            .with_fixed(fixed)
+            .with_obstacles(obstacles)
+            .with_clearance(clearance)
+            .with_balance(balance);
+        packing.check_lengths()?;
+
+        Ok(packing)
+    }
+
+    /// Circles without center are reported by their original index, as loading does.
+    fn check_centers(&self) -> Result<(), PackingIoError> {
+        match self
+            .inner_circles
+            .iter()
+            .zip(&self.original_indexes)
+            .find(|(circle, _)| circle.center.is_none())
+        {
+            Some((_, &index)) => Err(PackingIoError::MissingCenter { index }),
+            None => Ok(()),
+        }
+    }
+
+    /// Every circle must have an original index to be plotted.
+    pub(super) fn check_indexes(&self) -> Result<(), PackingIoError> {
+        if self.original_indexes.len() != self.inner_circles.len() {
+            return Err(PackingIoError::IndexesMismatch {
+                circles: self.inner_circles.len(),
+                indexes: self.original_indexes.len(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Every circle must have an original index, a fixed flag (unless none is fixed) and a
+    /// balance mass (unless masses are not custom) to be saved or loaded.
+    pub(super) fn check_lengths(&self) -> Result<(), PackingIoError> {
+        self.check_indexes()?;
+        let circles = self.inner_circles.len();
+        if !self.fixed.is_empty() && self.fixed.len() != circles {
+            return Err(PackingIoError::FixedMismatch {
+                circles,
+                flags: self.fixed.len(),
+            });
+        }
+        if let Some(balance) = &self.balance {
+            if !balance.masses.is_empty() && balance.masses.len() != circles {
+                return Err(PackingIoError::MassesMismatch {
+                    circles,
+                    masses: balance.masses.len(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn from_records(
+        container: Container,
+        inner_circles: Vec<Circle>,
+        original_indexes: Vec<usize>,
+    ) -> Result<Self, PackingIoError> {
+        // Subset packings (e.g. knapsack solutions) keep indexes of the candidate circles.
+        let mut seen = HashSet::with_capacity(original_indexes.len());
+        if !original_indexes.iter().all(|&index| seen.insert(index)) {
+            return Err(PackingIoError::InvalidIndexes);
+        }
+
+        Ok(Self::new(container, inner_circles).with_original_indexes(original_indexes))
+    }
+}
+
+fn parse_cell<T>(text: &str, line: usize) -> Result<T, PackingIoError>
+where
+    T: std::str::FromStr,
+    T::Err: fmt::Display,
+{
+    text.parse::<T>().map_err(|err| PackingIoError::Parse {
+        line,
+        message: format!("invalid value `{text}`: {err}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    fn packing() -> CirclesPacking {
+        let circles = vec![
+            Circle::new(1.0, Point { x: -2.5, y: 0.1 }),
+            Circle::new(
+                0.5,
+                Point {
+                    x: 1.0 / 3.0,
+                    y: -1.25,
+                },
+            ),
+            Circle::new(2.0, Point { x: 1.5, y: 2.0 }),
+        ];
+
+        CirclesPacking::new(Container::rectangle(12.0, 8.5), circles)
+            .with_original_indexes(vec![4, 0, 7])
+            .with_fixed(vec![false, true, false])
+            .with_obstacles(vec![Circle::new(0.75, Point { x: 3.0, y: -3.0 })])
+            .with_clearance(Clearance::new(0.01, 0.125))
+            .with_balance(Some(Balance::new(0.3).with_masses(vec![7.0, 0.5, 2.25])))
+    }
+
+    fn assert_same(loaded: &CirclesPacking, packing: &CirclesPacking) {
+        assert_eq!(loaded.container, packing.container);
+        assert_eq!(loaded.original_indexes, packing.original_indexes);
+        assert_eq!(loaded.fixed, packing.fixed);
+        assert_eq!(loaded.clearance, packing.clearance);
+        assert_eq!(loaded.balance, packing.balance);
+
+        let circles = |circles: &[Circle]| -> Vec<(FloatType, FloatType, FloatType)> {
+            circles
+                .iter()
+                .map(|c| (c.radius, c.center.unwrap().x, c.center.unwrap().y))
+                .collect()
+        };
+        assert_eq!(
+            circles(&loaded.inner_circles),
+            circles(&packing.inner_circles)
+        );
+        assert_eq!(circles(&loaded.obstacles), circles(&packing.obstacles));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let packing = packing();
+        let loaded = CirclesPacking::from_json(&packing.to_json().unwrap()).unwrap();
+
+        assert_same(&loaded, &packing);
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let packing = packing();
+        let loaded = CirclesPacking::from_csv(&packing.to_csv().unwrap()).unwrap();
+
+        assert_same(&loaded, &packing);
+    }
+
+    #[test]
+    fn circle_without_center_is_not_saved() {
+        let mut unfinished = packing();
+        unfinished.inner_circles[2].center = None;
+
+        for saved in [unfinished.to_json(), unfinished.to_csv()] {
+            assert!(matches!(
+                saved,
+                Err(PackingIoError::MissingCenter { index: 7 })
+            ));
+        }
+
+        // Loading rejects such a circle by the same index.
+        let mut record: serde_json::Value =
+            serde_json::from_str(&packing().to_json().unwrap()).unwrap();
+        record["circles"][2]["center"] = serde_json::Value::Null;
+        assert!(matches!(
+            CirclesPacking::from_json(&record.to_string()),
+            Err(PackingIoError::MissingCenter { index: 7 })
+        ));
+    }
+
+    #[test]
+    fn missing_original_indexes_are_rejected() {
+        let packing = packing().with_original_indexes(vec![]);
+
+        assert!(matches!(
+            packing.to_json(),
+            Err(PackingIoError::IndexesMismatch {
+                circles: 3,
+                indexes: 0
+            })
+        ));
+        assert!(packing.to_csv().is_err());
+
+        let path = std::env::temp_dir().join("missing_original_indexes.svg");
+        assert!(matches!(
+            packing.plot_svg(path.to_str().unwrap()),
+            Err(Error::Packing(PackingIoError::IndexesMismatch { .. }))
+        ));
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn wrong_number_of_masses_is_rejected() {
+        let mut short = packing();
+        short.balance = Some(Balance::new(0.3).with_masses(vec![1.0]));
+
+        assert!(matches!(
+            short.to_csv(),
+            Err(PackingIoError::MassesMismatch {
+                circles: 3,
+                masses: 1
+            })
+        ));
+
+        let mut record: serde_json::Value =
+            serde_json::from_str(&packing().to_json().unwrap()).unwrap();
+        record["balance"]["masses"] = serde_json::json!([1.0, 2.0]);
+        assert!(matches!(
+            CirclesPacking::from_json(&record.to_string()),
+            Err(PackingIoError::MassesMismatch {
+                circles: 3,
+                masses: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn short_fixed_mask_is_rejected() {
+        let packing = packing().with_fixed(vec![true]);
+
+        assert!(matches!(
+            packing.to_json(),
+            Err(PackingIoError::FixedMismatch {
+                circles: 3,
+                flags: 1
+            })
+        ));
+    }
+}
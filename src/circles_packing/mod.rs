@@ -0,0 +1,237 @@
+mod io;
+mod plot;
+
+use nalgebra::DVector;
+
+use crate::{
+    balance::Balance,
+    circle::Circle,
+    clearance::Clearance,
+    container::{Container, ContainerShape},
+    error::Error,
+    point::Point,
+    utils::{Float, FloatType},
+    validation::ValidationReport,
+};
+
+pub use self::io::{PackingFormat, PackingIoError};
+pub use self::plot::{CircleFill, CircleLabel, PlotOptions};
+
+pub struct CirclesPacking<T: Float = FloatType> {
+    pub container: Container<T>,
+    pub inner_circles: Vec<Circle<T>>,
+    /// Index of every inner circle in the original radiuses list.
+    pub original_indexes: Vec<usize>,
+    /// Whether every inner circle has a dictated position and must not move.
+    pub fixed: Vec<bool>,
+    /// Circular regions inner circles must not intersect, they may stick out of the container.
+    pub obstacles: Vec<Circle<T>>,
+    pub clearance: Clearance<T>,
+    /// Centre of mass constraint, masses follow the order of inner circles.
+    pub balance: Option<Balance<T>>,
+}
+
+impl<T: Float> CirclesPacking<T> {
+    pub fn new(container: Container<T>, inner_circles: Vec<Circle<T>>) -> Self {
+        let original_indexes = (0..inner_circles.len()).collect();
+        let fixed = vec![false; inner_circles.len()];
+
+        Self {
+            container,
+            inner_circles,
+            original_indexes,
+            fixed,
+            obstacles: Vec::new(),
+            clearance: Clearance::default(),
+            balance: None,
+        }
+    }
+
+    /// One index per inner circle, saving fails otherwise.
+    pub fn with_original_indexes(mut self, original_indexes: Vec<usize>) -> Self {
+        self.original_indexes = original_indexes;
+        self
+    }
+
+    /// One flag per inner circle or none, saving fails otherwise.
+    pub fn with_fixed(mut self, fixed: Vec<bool>) -> Self {
+        self.fixed = fixed;
+        self
+    }
+
+    pub fn with_obstacles(mut self, obstacles: Vec<Circle<T>>) -> Self {
+        self.obstacles = obstacles;
+        self
+    }
+
+    pub fn with_clearance(mut self, clearance: Clearance<T>) -> Self {
+        self.clearance = clearance;
+        self
+    }
+
+    pub fn with_balance(mut self, balance: Option<Balance<T>>) -> Self {
+        self.balance = balance;
+        self
+    }
+
+    pub fn is_fixed(&self, index: usize) -> bool {
+        self.fixed.get(index).copied().unwrap_or(false)
+    }
+
+    pub fn from_coords_vec_and_radiuses(x: DVector<T>, radiuses: &[T]) -> Result<Self, Error> {
+        Self::from_coords_vec(ContainerShape::Circle, x, radiuses)
+    }
+
+    /// Packing from `(x_1, ..., x_n, y_1, ..., y_n, size)` variables of the container with the given shape.
+    pub fn from_coords_vec(
+        shape: ContainerShape<T>,
+        x: DVector<T>,
+        radiuses: &[T],
+    ) -> Result<Self, Error> {
+        let n = radiuses.len();
+        if x.len() != 2 * n + 1 {
+            return Err(Error::DimensionMismatch {
+                expected: 2 * n + 1,
+                found: x.len(),
+            });
+        }
+
+        if x.iter().any(|value| !value.is_finite()) {
+            return Err(Error::NotANumber);
+        }
+
+        let container = Container::new(shape, x[2 * n].clone());
+
+        let inner_circles = (0..n)
+            .map(|i| {
+                Circle::new(
+                    radiuses[i].clone(),
+                    Point {
+                        x: x[i].clone(),
+                        y: x[n + i].clone(),
+                    },
+                )
+            })
+            .collect();
+
+        Ok(Self::new(container, inner_circles))
+    }
+
+    /// `(x_1, ..., x_n, y_1, ..., y_n, size)` variables, every inner circle must have a center.
+    pub fn into_coords_vec(&self) -> Result<DVector<T>, Error> {
+        let centers = self.centers()?;
+        let data: Vec<T> = ([
+            Vec::from_iter(centers.iter().map(|center| center.x.clone())),
+            Vec::from_iter(centers.iter().map(|center| center.y.clone())),
+            vec![self.container.size.clone()],
+        ])
+        .concat();
+
+        if data.iter().any(|value| !value.is_finite()) {
+            return Err(Error::NotANumber);
+        }
+
+        Ok(DVector::from_vec(data))
+    }
+
+    /// Centers of inner circles.
+    pub fn centers(&self) -> Result<Vec<Point<T>>, Error> {
+        self.inner_circles
+            .iter()
+            .enumerate()
+            .map(|(index, circle)| circle.center.clone().ok_or(Error::MissingCenter { index }))
+            .collect()
+    }
+
+    /// Same as [`validate`](Self::validate) without tolerance, touching circles are valid.
+    pub fn is_valid_pack(&self) -> bool {
+        self.validate(T::zero()).is_valid()
+    }
+
+    /// Detailed report of constraints violated by more than `tolerance`.
+    pub fn validate(&self, tolerance: T) -> ValidationReport<T> {
+        let report = ValidationReport::with_clearance(
+            &self.container,
+            &self.inner_circles,
+            &self.obstacles,
+            &self.clearance,
+            tolerance,
+        );
+
+        match &self.balance {
+            Some(balance) => report.with_balance(&self.inner_circles, balance),
+            None => report,
+        }
+    }
+
+    pub fn print(&self) -> Result<(), Error> {
+        let centers = self.centers()?;
+
+        println!("{}", self.container.size);
+        println!("{}", self.is_valid_pack());
+        for (circle, center) in self.inner_circles.iter().zip(centers) {
+            println!("{} {:.15} {:.15}", circle.radius, center.x, center.y);
+        }
+        println!();
+
+        Ok(())
+    }
+
+    pub fn inner_circles_radiuses(&self) -> Vec<T> {
+        self.inner_circles
+            .iter()
+            .map(|c| c.radius.clone())
+            .collect::<Vec<_>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coords_vec_must_match_radiuses() {
+        let radiuses = [1.0, 2.0];
+
+        assert!(matches!(
+            CirclesPacking::from_coords_vec_and_radiuses(DVector::zeros(0), &radiuses),
+            Err(Error::DimensionMismatch {
+                expected: 5,
+                found: 0
+            })
+        ));
+        assert!(matches!(
+            CirclesPacking::from_coords_vec_and_radiuses(
+                DVector::from_vec(vec![0.0, 3.0, 0.0, 0.0, FloatType::NAN]),
+                &radiuses
+            ),
+            Err(Error::NotANumber)
+        ));
+
+        let packing = CirclesPacking::from_coords_vec_and_radiuses(
+            DVector::from_vec(vec![-1.0, 2.0, 0.0, 0.0, 4.0]),
+            &radiuses,
+        )
+        .unwrap();
+        assert_eq!(packing.container.size, 4.0);
+        assert_eq!(packing.inner_circles_radiuses(), radiuses);
+    }
+
+    #[test]
+    fn masses_must_match_circles() {
+        let report = |masses| {
+            CirclesPacking::from_coords_vec_and_radiuses(
+                DVector::from_vec(vec![-1.0, 2.0, 0.0, 0.0, 4.0]),
+                &[1.0, 2.0],
+            )
+            .unwrap()
+            .with_balance(Some(Balance::new(0.5).with_masses(masses)))
+            .validate(1e-9)
+        };
+        assert!(report(vec![2.0, 1.0]).is_balanced());
+
+        let truncated = report(vec![2.0]);
+        assert!(!truncated.is_balanced());
+        assert_eq!(truncated.imbalance, FloatType::INFINITY);
+    }
+}
@@ -0,0 +1,432 @@
+use plotters::{
+    chart::ChartBuilder,
+    coord::Shift,
+    drawing::{DrawingArea, IntoDrawingArea},
+    element::{PathElement, Rectangle},
+    prelude::{BitMapBackend, DrawingBackend, EmptyElement, SVGBackend, Text},
+    style::{
+        colors::colormaps::ViridisRGB,
+        full_palette::{GREEN_800, GREY_400, GREY_600, GREY_800},
+        text_anchor::{HPos, Pos, VPos},
+        Color, IntoFont, RGBAColor, ShapeStyle, TextStyle, BLACK, RED, WHITE,
+    },
+};
+
+use crate::{container::ContainerShape, error::Error, point::Point, utils::FloatType};
+
+use super::CirclesPacking;
+
+/// Text drawn in the center of every inner circle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircleLabel {
+    /// Index of the circle in the original radiuses list.
+    Index,
+    Radius,
+}
+
+/// How inner circles are filled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CircleFill {
+    #[default]
+    None,
+    ByRadius,
+    /// By index of the circle in the original radiuses list.
+    ByIndex,
+}
+
+#[derive(Clone, Debug)]
+pub struct PlotOptions {
+    image_size: u32,
+    font: String,
+    fill: CircleFill,
+    label: Option<CircleLabel>,
+    contact_tolerance: Option<FloatType>,
+    highlight_violations: bool,
+    violation_tolerance: FloatType,
+}
+
+impl Default for PlotOptions {
+    fn default() -> Self {
+        Self {
+            image_size: 1000,
+            font: "bebas neue".to_string(),
+            fill: CircleFill::None,
+            label: None,
+            contact_tolerance: None,
+            highlight_violations: true,
+            violation_tolerance: 0.0,
+        }
+    }
+}
+
+impl PlotOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn image_size(mut self, image_size: u32) -> Self {
+        self.image_size = image_size;
+        self
+    }
+
+    pub fn font(mut self, font: &str) -> Self {
+        self.font = font.to_string();
+        self
+    }
+
+    pub fn fill(mut self, fill: CircleFill) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    pub fn label(mut self, label: Option<CircleLabel>) -> Self {
+        self.label = label;
+        self
+    }
+
+    /// Draw a line between centers of circles which gap differs from the pair clearance by not
+    /// more than `tolerance`.
+    pub fn contact_tolerance(mut self, contact_tolerance: Option<FloatType>) -> Self {
+        self.contact_tolerance = contact_tolerance;
+        self
+    }
+
+    /// Draw in red circles overlapping others or sticking out of the main circle.
+    pub fn highlight_violations(mut self, highlight_violations: bool) -> Self {
+        self.highlight_violations = highlight_violations;
+        self
+    }
+
+    /// Violations not bigger than `tolerance` are not highlighted, see
+    /// [`CirclesPacking::validate`].
+    pub fn violation_tolerance(mut self, violation_tolerance: FloatType) -> Self {
+        self.violation_tolerance = violation_tolerance;
+        self
+    }
+}
+
+impl CirclesPacking {
+    pub fn plot(&self, path: &str) -> Result<(), Error> {
+        self.plot_with_options(path, &PlotOptions::default())
+    }
+
+    pub fn plot_with_options(&self, path: &str, options: &PlotOptions) -> Result<(), Error> {
+        let size = (options.image_size, options.image_size);
+        let root = BitMapBackend::new(path, size).into_drawing_area();
+        self.draw(&root, options)
+    }
+
+    /// Plot packing as svg, so it can be zoomed in without losing quality.
+    pub fn plot_svg(&self, path: &str) -> Result<(), Error> {
+        self.plot_svg_with_options(path, &PlotOptions::default())
+    }
+
+    pub fn plot_svg_with_options(&self, path: &str, options: &PlotOptions) -> Result<(), Error> {
+        let size = (options.image_size, options.image_size);
+        let root = SVGBackend::new(path, size).into_drawing_area();
+        self.draw(&root, options)
+    }
+
+    /// Every inner circle must have a center and an original index, obstacles without centers
+    /// are skipped. Failures of single elements (e.g. a missing font) do not stop plotting.
+    fn draw<DB: DrawingBackend>(
+        &self,
+        root: &DrawingArea<DB, Shift>,
+        options: &PlotOptions,
+    ) -> Result<(), Error> {
+        let centers = self.centers()?;
+        self.check_indexes()?;
+        let plot_error = |err: plotters::drawing::DrawingAreaErrorKind<DB::ErrorType>| {
+            Error::Plot(err.to_string())
+        };
+
+        let img_size = options.image_size;
+        let scale = img_size as FloatType / 1000.0;
+        let label_area_size = (85.0 * scale) as u32;
+        let (half_width, half_height) = self.container.half_extents();
+        let plot_size = half_width.max(half_height) * 1.1;
+
+        root.fill(&WHITE).map_err(plot_error)?;
+
+        let mut chart = ChartBuilder::on(root)
+            .set_all_label_area_size(label_area_size)
+            .build_cartesian_2d(-plot_size..plot_size, -plot_size..plot_size)
+            .map_err(plot_error)?;
+
+        chart
+            .configure_mesh()
+            .disable_mesh()
+            .x_labels(10)
+            .y_labels(10)
+            .label_style(TextStyle::from((options.font.as_str(), 30.0 * scale)))
+            .draw()
+            .ok();
+
+        let root = chart.plotting_area();
+
+        let convert_radius = |radius: FloatType| {
+            (radius * (img_size - 2 * label_area_size) as FloatType) / (2.0 * plot_size)
+        };
+
+        let fill_circle = |center: Point, radius: FloatType, fill: RGBAColor| {
+            EmptyElement::at((center.x, center.y))
+                + plotters::element::Circle::new(
+                    (0, 0),
+                    convert_radius(radius),
+                    ShapeStyle {
+                        color: fill,
+                        filled: true,
+                        stroke_width: 0,
+                    },
+                )
+        };
+
+        let set_circle = |center: Point, radius: FloatType, color: RGBAColor| {
+            EmptyElement::at((center.x, center.y))
+                + plotters::element::Circle::new(
+                    (0, 0),
+                    3,
+                    ShapeStyle {
+                        color,
+                        filled: true,
+                        stroke_width: 2,
+                    },
+                )
+                + plotters::element::Circle::new(
+                    (0, 0),
+                    convert_radius(radius),
+                    ShapeStyle {
+                        color,
+                        filled: false,
+                        stroke_width: 2,
+                    },
+                )
+        };
+
+        match self.container.shape {
+            ContainerShape::Circle => {
+                let center = Point { x: 0.0, y: 0.0 };
+                root.draw(&set_circle(center, self.container.size, GREEN_800.mix(0.8)))
+                    .ok();
+            }
+            ContainerShape::Square
+            | ContainerShape::Rectangle { .. }
+            | ContainerShape::Strip { .. } => {
+                root.draw(&Rectangle::new(
+                    [(-half_width, half_height), (half_width, -half_height)],
+                    ShapeStyle {
+                        color: GREEN_800.mix(0.8),
+                        filled: false,
+                        stroke_width: 2,
+                    },
+                ))
+                .ok();
+            }
+        }
+
+        for obstacle in &self.obstacles {
+            let Some(center) = obstacle.center else {
+                continue;
+            };
+
+            root.draw(&fill_circle(center, obstacle.radius, GREY_600.mix(0.6)))
+                .ok();
+            root.draw(&set_circle(center, obstacle.radius, GREY_800.mix(0.8)))
+                .ok();
+        }
+
+        let mut violations = vec![false; self.inner_circles.len()];
+        if options.highlight_violations {
+            for index in self
+                .validate(options.violation_tolerance)
+                .violating_circles()
+            {
+                violations[index] = true;
+            }
+        }
+
+        let (min_radius, max_radius) = self.inner_circles.iter().fold(
+            (FloatType::INFINITY, FloatType::NEG_INFINITY),
+            |(min, max), c| (min.min(c.radius), max.max(c.radius)),
+        );
+        let max_index = self.original_indexes.len().saturating_sub(1).max(1);
+
+        for (i, c) in self.inner_circles.iter().enumerate() {
+            let fill = match options.fill {
+                CircleFill::None => None,
+                CircleFill::ByRadius => Some(ViridisRGB::get_color_normalized(
+                    c.radius,
+                    min_radius,
+                    max_radius.max(min_radius + FloatType::EPSILON),
+                )),
+                CircleFill::ByIndex => Some(ViridisRGB::get_color_normalized(
+                    self.original_indexes[i] as FloatType,
+                    0.0,
+                    max_index as FloatType,
+                )),
+            };
+
+            let (color, fill) = if violations[i] {
+                (RED.mix(0.9), Some(RED.mix(0.4)))
+            } else if self.is_fixed(i) {
+                (GREY_800.mix(0.8), Some(fill.unwrap_or(GREY_400).mix(0.6)))
+            } else {
+                (GREEN_800.mix(0.8), fill.map(|fill| fill.mix(0.6)))
+            };
+
+            if let Some(fill) = fill {
+                root.draw(&fill_circle(centers[i], c.radius, fill)).ok();
+            }
+            root.draw(&set_circle(centers[i], c.radius, color)).ok();
+        }
+
+        if let Some(tolerance) = options.contact_tolerance {
+            for (i, c1) in self.inner_circles.iter().enumerate() {
+                for (j, c2) in self.inner_circles.iter().enumerate().skip(i + 1) {
+                    let gap = c1.distance(c2) - c1.radius - c2.radius - self.clearance.pair;
+                    if gap.abs() <= tolerance {
+                        let (p1, p2) = (centers[i], centers[j]);
+                        root.draw(&PathElement::new(
+                            vec![(p1.x, p1.y), (p2.x, p2.y)],
+                            GREY_600.stroke_width((2.0 * scale).max(1.0) as u32),
+                        ))
+                        .ok();
+                    }
+                }
+            }
+        }
+
+        if let Some(label) = options.label {
+            for ((c, index), center) in self
+                .inner_circles
+                .iter()
+                .zip(&self.original_indexes)
+                .zip(&centers)
+            {
+                let text = match label {
+                    CircleLabel::Index => index.to_string(),
+                    CircleLabel::Radius => format!("{}", (c.radius * 1e3).round() / 1e3),
+                };
+                let font_size = (convert_radius(c.radius) * 0.6).clamp(4.0, 30.0 * scale);
+                let style = (options.font.as_str(), font_size)
+                    .into_font()
+                    .color(&BLACK)
+                    .pos(Pos::new(HPos::Center, VPos::Center));
+
+                root.draw(&Text::new(text, (center.x, center.y), style))
+                    .ok();
+            }
+        }
+
+        root.present().map_err(plot_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{balance::Balance, circle::Circle, clearance::Clearance, container::Container};
+
+    /// Two circles of radius 1 with centers `distance` apart in a container of radius 5.
+    fn pair(distance: FloatType) -> CirclesPacking {
+        CirclesPacking::new(
+            Container::circle(5.0),
+            vec![
+                Circle::new(
+                    1.0,
+                    Point {
+                        x: -distance / 2.0,
+                        y: 0.0,
+                    },
+                ),
+                Circle::new(
+                    1.0,
+                    Point {
+                        x: distance / 2.0,
+                        y: 0.0,
+                    },
+                ),
+            ],
+        )
+    }
+
+    fn svg(packing: &CirclesPacking, options: &PlotOptions) -> Result<String, Error> {
+        let mut svg = String::new();
+        {
+            let root = SVGBackend::with_string(&mut svg, (200, 200)).into_drawing_area();
+            packing.draw(&root, options)?;
+        }
+
+        Ok(svg)
+    }
+
+    #[test]
+    fn masses_of_wrong_length_do_not_stop_plotting() {
+        let packing = pair(2.0).with_balance(Some(Balance::new(0.1).with_masses(vec![1.0])));
+
+        assert!(svg(&packing, &PlotOptions::new()).is_ok());
+    }
+
+    #[test]
+    fn circles_touching_at_pair_clearance_get_contact_line() {
+        let lines = |pair_clearance, tolerance| {
+            let packing = pair(2.5).with_clearance(Clearance::new(pair_clearance, 0.0));
+            let options = PlotOptions::new().contact_tolerance(tolerance);
+            svg(&packing, &options)
+                .unwrap()
+                .matches("<polyline")
+                .count()
+        };
+
+        let without_contacts = lines(0.5, None);
+        assert_eq!(lines(0.5, Some(1e-9)), without_contacts + 1);
+        assert_eq!(lines(0.2, Some(1e-9)), without_contacts);
+    }
+
+    /// Contents of all text elements, axis labels included.
+    fn texts(svg: &str) -> Vec<String> {
+        svg.split("<text")
+            .skip(1)
+            .filter_map(|text| {
+                let (_, rest) = text.split_once('>')?;
+                let (content, _) = rest.split_once("</text>")?;
+                Some(content.trim().to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_circle_is_drawn_with_its_label() {
+        let packing = CirclesPacking::new(
+            Container::circle(5.0),
+            vec![
+                Circle::new(1.25, Point { x: -2.0, y: 0.0 }),
+                Circle::new(0.75, Point { x: 2.0, y: 0.0 }),
+            ],
+        )
+        .with_original_indexes(vec![17, 42])
+        .with_obstacles(vec![
+            Circle::new(0.5, Point { x: 0.0, y: 3.0 }),
+            Circle::new(0.5, Point { x: 0.0, y: -3.0 }),
+        ]);
+        let options = PlotOptions::new().image_size(200);
+
+        let unlabelled = svg(&packing, &options).unwrap();
+        // Outlines of the container, the obstacles and the inner circles.
+        let outlines = unlabelled
+            .lines()
+            .filter(|line| line.starts_with("<circle") && line.contains("fill=\"none\""))
+            .count();
+        assert_eq!(outlines, 1 + 2 + 2);
+
+        let labels = |label| {
+            let options = PlotOptions::new().image_size(200).label(Some(label));
+            texts(&svg(&packing, &options).unwrap())
+        };
+        let indexes = labels(CircleLabel::Index);
+        assert!(indexes.contains(&"17".to_string()) && indexes.contains(&"42".to_string()));
+        let radiuses = labels(CircleLabel::Radius);
+        assert!(radiuses.contains(&"1.25".to_string()) && radiuses.contains(&"0.75".to_string()));
+        assert!(!texts(&unlabelled).contains(&"17".to_string()));
+    }
+}
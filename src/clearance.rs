@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    container::{Container, ContainerShape},
+    utils::{cast, Float, FloatType},
+};
+
+/// Minimum gaps required between inner circles and between inner circles and the container wall.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Clearance<T: Float = FloatType> {
+    /// Gap between every two inner circles, also kept from obstacles.
+    #[serde(default)]
+    pub pair: T,
+    /// Gap between inner circles and the container wall.
+    #[serde(default)]
+    pub wall: T,
+}
+
+impl<T: Float> Clearance<T> {
+    pub fn new(pair: T, wall: T) -> Self {
+        Self { pair, wall }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.pair.is_zero() && self.wall.is_zero()
+    }
+
+    /// Radius grown by half of the pair clearance, grown circles only have to touch each other.
+    pub fn inflate(&self, radius: T) -> T {
+        radius + self.pair.clone() / cast(2.0)
+    }
+
+    /// Container grown circles have to fit into, so that the original circles keep the wall
+    /// clearance.
+    pub fn inflate_container(&self, container: &Container<T>) -> Container<T> {
+        let offset = self.pair.clone() / cast(2.0) - self.wall.clone();
+        let double_offset = cast::<T>(2.0) * offset.clone();
+        let size = container.size.clone();
+
+        match &container.shape {
+            ContainerShape::Circle => Container::circle(size + offset),
+            ContainerShape::Square => Container::square(size + double_offset),
+            ContainerShape::Rectangle { width } => {
+                Container::rectangle(width.clone() + double_offset.clone(), size + double_offset)
+            }
+            ContainerShape::Strip { width } => {
+                Container::strip(width.clone() + double_offset.clone(), size + double_offset)
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    circle::Circle,
+    point::Point,
+    utils::{cast, Float, FloatType},
+};
+
+/// Shape of the container without its minimised size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "snake_case")]
+pub enum ContainerShape<T: Float = FloatType> {
+    /// Circle centered at (0, 0), the size is its radius.
+    #[default]
+    Circle,
+    /// Square centered at (0, 0), the size is its side.
+    Square,
+    /// Rectangle of fixed width centered at (0, 0), the size is its height.
+    Rectangle { width: T },
+    /// Strip of fixed width (along y) centered at (0, 0), the size is its length (along x).
+    Strip { width: T },
+}
+
+/// Container all inner circles are packed into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Container<T: Float = FloatType> {
+    pub shape: ContainerShape<T>,
+    /// Minimised size: radius of a circle, side of a square or height of a rectangle.
+    pub size: T,
+}
+
+impl<T: Float> Container<T> {
+    pub fn new(shape: ContainerShape<T>, size: T) -> Self {
+        Self { shape, size }
+    }
+
+    pub fn circle(radius: T) -> Self {
+        Self::new(ContainerShape::Circle, radius)
+    }
+
+    pub fn square(side: T) -> Self {
+        Self::new(ContainerShape::Square, side)
+    }
+
+    pub fn rectangle(width: T, height: T) -> Self {
+        Self::new(ContainerShape::Rectangle { width }, height)
+    }
+
+    pub fn strip(width: T, length: T) -> Self {
+        Self::new(ContainerShape::Strip { width }, length)
+    }
+
+    /// Half width and half height of the bounding box.
+    pub fn half_extents(&self) -> (T, T) {
+        let half = |value: &T| value.clone() / cast(2.0);
+
+        match &self.shape {
+            ContainerShape::Circle => (self.size.clone(), self.size.clone()),
+            ContainerShape::Square => (half(&self.size), half(&self.size)),
+            ContainerShape::Rectangle { width } => (half(width), half(&self.size)),
+            ContainerShape::Strip { width } => (half(&self.size), half(width)),
+        }
+    }
+
+    /// How far circle with the given center and radius sticks out of the container,
+    /// negative when it is inside.
+    pub fn excess(&self, center: Point<T>, radius: T) -> T {
+        match self.shape {
+            ContainerShape::Circle => center.x.hypot(center.y) + radius - self.size.clone(),
+            ContainerShape::Square
+            | ContainerShape::Rectangle { .. }
+            | ContainerShape::Strip { .. } => {
+                let (half_width, half_height) = self.half_extents();
+                (center.x.abs() + radius.clone() - half_width)
+                    .max(center.y.abs() + radius - half_height)
+            }
+        }
+    }
+
+    pub fn contains(&self, circle: &Circle<T>) -> bool {
+        match self.shape {
+            ContainerShape::Circle => circle.is_inside_main_circle(self.size.clone()),
+            ContainerShape::Square
+            | ContainerShape::Rectangle { .. }
+            | ContainerShape::Strip { .. } => circle.center.as_ref().is_some_and(|center| {
+                self.excess(center.clone(), circle.radius.clone()) <= T::zero()
+            }),
+        }
+    }
+
+    /// Whether circle fits into the container keeping at least `wall` gap from its boundary.
+    pub fn contains_with_clearance(&self, circle: &Circle<T>, wall: T) -> bool {
+        match self.shape {
+            ContainerShape::Circle => circle.is_inside_main_circle(self.size.clone() - wall),
+            ContainerShape::Square
+            | ContainerShape::Rectangle { .. }
+            | ContainerShape::Strip { .. } => circle.center.as_ref().is_some_and(|center| {
+                self.excess(center.clone(), circle.radius.clone() + wall.clone()) <= T::zero()
+            }),
+        }
+    }
+
+    /// Minimal size of the container of the same shape holding the given circle.
+    ///
+    /// Rectangle and strip can not hold the circle if it does not fit into their width.
+    pub fn required_size(&self, center: Point<T>, radius: T) -> T {
+        match self.shape {
+            ContainerShape::Circle => center.x.hypot(center.y) + radius,
+            ContainerShape::Square => {
+                cast::<T>(2.0) * (center.x.abs().max(center.y.abs()) + radius)
+            }
+            ContainerShape::Rectangle { .. } => cast::<T>(2.0) * (center.y.abs() + radius),
+            ContainerShape::Strip { .. } => cast::<T>(2.0) * (center.x.abs() + radius),
+        }
+    }
+
+    /// Move center inside the container, so that circle with the given radius fits into it.
+    pub fn clamp(&self, center: Point<T>, radius: T) -> Point<T> {
+        match self.shape {
+            ContainerShape::Circle => {
+                let distance = center.x.clone().hypot(center.y.clone());
+                let allowed = (self.size.clone() - radius).max(T::zero());
+
+                if distance > allowed {
+                    let scale = allowed / distance;
+                    Point {
+                        x: center.x * scale.clone(),
+                        y: center.y * scale,
+                    }
+                } else {
+                    center
+                }
+            }
+            ContainerShape::Square
+            | ContainerShape::Rectangle { .. }
+            | ContainerShape::Strip { .. } => {
+                let (half_width, half_height) = self.half_extents();
+                let (max_x, max_y) = (
+                    (half_width - radius.clone()).max(T::zero()),
+                    (half_height - radius).max(T::zero()),
+                );
+
+                Point {
+                    x: center.x.clamp(-max_x.clone(), max_x),
+                    y: center.y.clamp(-max_y.clone(), max_y),
+                }
+            }
+        }
+    }
+}
@@ -1,54 +1,387 @@
+use std::time::Instant;
+
 use nalgebra::DVector;
 
-use crate::{ralgo::RAlgorithm, utils::FloatType};
+use crate::{
+    error::Error,
+    observer::{NoopObserver, Observer, Progress, Signal, Stage},
+    ralgo::{Objective, RAlgorithm, TerminationReason},
+    utils::{cast, to_float_type, Float, FloatType},
+};
+
+/// Why the dichotomy step ralgo stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DichotomyTermination {
+    /// Step size fell below its minimum without improvement.
+    MinStepSize,
+    /// Observer requested to stop.
+    Interrupted,
+    /// Ralgo ran into NaN, `x` is the last accepted point.
+    NotANumber,
+}
+
+#[derive(Clone, Debug)]
+pub struct DichotomyResult<T: Float = FloatType> {
+    /// Best accepted point.
+    pub x: DVector<T>,
+    pub termination: DichotomyTermination,
+}
 
-fn last(x: &DVector<FloatType>) -> FloatType {
-    x[x.len() - 1]
+fn last<T: Float>(x: &DVector<T>) -> T {
+    x[x.len() - 1].clone()
 }
 
-pub fn dichotomy_step_ralgo(
-    mut x: DVector<FloatType>,
+pub fn dichotomy_step_ralgo<O: Objective<T>, T: Float>(
+    x: DVector<T>,
     reset_step: bool,
-    eps: FloatType,
-    ralgo: RAlgorithm,
-) -> DVector<FloatType> {
-    let mut step_size = 40.96;
+    eps: T,
+    ralgo: RAlgorithm<O, T>,
+) -> Result<DichotomyResult<T>, Error> {
+    dichotomy_step_ralgo_with_observer(x, reset_step, eps, ralgo, &mut NoopObserver)
+}
 
-    while step_size >= 0.01 {
-        println!("step = {step_size}");
-        let y = ralgo.evaluate(x.clone(), step_size);
+pub fn dichotomy_step_ralgo_with_observer<O: Objective<T>, T: Float>(
+    mut x: DVector<T>,
+    reset_step: bool,
+    eps: T,
+    ralgo: RAlgorithm<O, T>,
+    observer: &mut dyn Observer,
+) -> Result<DichotomyResult<T>, Error> {
+    ralgo.check_variables(&x)?;
+
+    let start_time = Instant::now();
+    let mut step_size: T = cast(40.96);
+    let mut iteration = 0;
+
+    let termination = loop {
+        if step_size < cast(0.01) {
+            break DichotomyTermination::MinStepSize;
+        }
+
+        let progress = Progress {
+            stage: Stage::Dichotomy {
+                step_size: to_float_type(step_size.clone()),
+            },
+            iteration,
+            best_value: to_float_type(last(&x)),
+            elapsed: start_time.elapsed(),
+        };
+        if observer.on_iteration(&progress) == Signal::Stop {
+            break DichotomyTermination::Interrupted;
+        }
+        iteration += 1;
+
+        // Steps accepted before NaN are kept, ralgo failing later does not spoil them.
+        let result = match ralgo.evaluate_with_observer(x.clone(), step_size.clone(), observer) {
+            Ok(result) => result,
+            Err(Error::NotANumber) => break DichotomyTermination::NotANumber,
+            Err(err) => return Err(err),
+        };
+        let y = result.x;
 
         if (last(&x) - last(&y)) / last(&x) > eps {
             x = y;
             if reset_step {
-                step_size = 40.96;
+                step_size = cast(40.96);
+            }
+
+            let progress = Progress {
+                best_value: to_float_type(last(&x)),
+                elapsed: start_time.elapsed(),
+                ..progress
+            };
+            if observer.on_improvement(&progress) == Signal::Stop {
+                break DichotomyTermination::Interrupted;
             }
         } else {
-            step_size /= 2.0;
+            step_size /= cast(2.0);
         }
-    }
 
-    x
+        if result.termination == TerminationReason::Interrupted {
+            break DichotomyTermination::Interrupted;
+        }
+    };
+
+    Ok(DichotomyResult { x, termination })
 }
 
-pub fn smart_dichotomy_step_ralgo(
-    mut x: DVector<FloatType>,
-    eps: FloatType,
-    ralgo: RAlgorithm,
-) -> DVector<FloatType> {
-    let mut step_size = 40.96;
+pub fn smart_dichotomy_step_ralgo<O: Objective<T>, T: Float>(
+    x: DVector<T>,
+    eps: T,
+    ralgo: RAlgorithm<O, T>,
+) -> Result<DichotomyResult<T>, Error> {
+    smart_dichotomy_step_ralgo_with_observer(x, eps, ralgo, &mut NoopObserver)
+}
 
-    while step_size >= 0.01 {
-        println!("step = {step_size}");
-        let y = ralgo.evaluate(x.clone(), step_size);
+pub fn smart_dichotomy_step_ralgo_with_observer<O: Objective<T>, T: Float>(
+    mut x: DVector<T>,
+    eps: T,
+    ralgo: RAlgorithm<O, T>,
+    observer: &mut dyn Observer,
+) -> Result<DichotomyResult<T>, Error> {
+    ralgo.check_variables(&x)?;
+
+    let start_time = Instant::now();
+    let mut step_size: T = cast(40.96);
+    let mut iteration = 0;
+
+    let termination = loop {
+        if step_size < cast(0.01) {
+            break DichotomyTermination::MinStepSize;
+        }
+
+        let progress = Progress {
+            stage: Stage::Dichotomy {
+                step_size: to_float_type(step_size.clone()),
+            },
+            iteration,
+            best_value: to_float_type(last(&x)),
+            elapsed: start_time.elapsed(),
+        };
+        if observer.on_iteration(&progress) == Signal::Stop {
+            break DichotomyTermination::Interrupted;
+        }
+        iteration += 1;
+
+        // Steps accepted before NaN are kept, ralgo failing later does not spoil them.
+        let result = match ralgo.evaluate_with_observer(x.clone(), step_size.clone(), observer) {
+            Ok(result) => result,
+            Err(Error::NotANumber) => break DichotomyTermination::NotANumber,
+            Err(err) => return Err(err),
+        };
+        let y = result.x;
 
         if (last(&x) - last(&y)) / last(&x) > eps {
             x = y;
-            step_size = (step_size * 2.0).min(40.96);
+            step_size = (step_size.clone() * cast(2.0)).min(cast(40.96));
+
+            let progress = Progress {
+                best_value: to_float_type(last(&x)),
+                elapsed: start_time.elapsed(),
+                ..progress
+            };
+            if observer.on_improvement(&progress) == Signal::Stop {
+                break DichotomyTermination::Interrupted;
+            }
         } else {
-            step_size /= 2.0;
+            step_size /= cast(2.0);
+        }
+
+        if result.termination == TerminationReason::Interrupted {
+            break DichotomyTermination::Interrupted;
         }
+    };
+
+    Ok(DichotomyResult { x, termination })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::{
+        calcfg::CirclesPackingObjective, circles_packing::CirclesPacking,
+        container::ContainerShape, heuristic_algo::HeuristicAlgorithmBuilder,
+        ralgo::RAlgorithmBuilder,
+    };
+
+    /// `|x|` which turns into NaN after the given number of evaluations.
+    struct NanAfter {
+        evaluations: Cell<usize>,
+        limit: usize,
     }
 
-    x
+    impl Objective for NanAfter {
+        fn value(&self, x: &DVector<FloatType>) -> FloatType {
+            self.evaluations.set(self.evaluations.get() + 1);
+            if self.evaluations.get() > self.limit {
+                FloatType::NAN
+            } else {
+                x[0].abs()
+            }
+        }
+
+        fn subgradient(&self, x: &DVector<FloatType>) -> DVector<FloatType> {
+            DVector::from_element(1, x[0].signum())
+        }
+
+        fn dimension(&self) -> usize {
+            1
+        }
+    }
+
+    #[test]
+    fn nan_keeps_accepted_steps() {
+        // Every ralgo run makes one step, the third one from 10 with the step 10.24 is accepted
+        // and the fourth one runs into NaN.
+        let ralgo = RAlgorithmBuilder::new()
+            .max_iterations(1)
+            .objective(NanAfter {
+                evaluations: Cell::new(0),
+                limit: 6,
+            })
+            .build();
+
+        let result =
+            dichotomy_step_ralgo(DVector::from_element(1, 10.0), false, 1e-3, ralgo).unwrap();
+
+        assert_eq!(result.termination, DichotomyTermination::NotANumber);
+        assert!((result.x[0] - (10.0 - 10.24)).abs() < 1e-12);
+    }
+
+    /// Observer stopping at the given call of `on_iteration` in the given stage, or at the
+    /// first improvement.
+    struct StopAt {
+        ralgo: bool,
+        calls: usize,
+        limit: usize,
+        on_improvement: bool,
+    }
+
+    impl StopAt {
+        fn iteration(ralgo: bool, limit: usize) -> Self {
+            Self {
+                ralgo,
+                calls: 0,
+                limit,
+                on_improvement: false,
+            }
+        }
+
+        fn improvement(ralgo: bool) -> Self {
+            Self {
+                on_improvement: true,
+                ..Self::iteration(ralgo, usize::MAX)
+            }
+        }
+
+        fn watches(&self, progress: &Progress) -> bool {
+            (progress.stage == Stage::RAlgorithm) == self.ralgo
+        }
+    }
+
+    impl Observer for StopAt {
+        fn on_iteration(&mut self, progress: &Progress) -> Signal {
+            if !self.watches(progress) {
+                return Signal::Continue;
+            }
+
+            self.calls += 1;
+            if self.calls == self.limit {
+                Signal::Stop
+            } else {
+                Signal::Continue
+            }
+        }
+
+        fn on_improvement(&mut self, progress: &Progress) -> Signal {
+            if self.on_improvement && self.watches(progress) {
+                Signal::Stop
+            } else {
+                Signal::Continue
+            }
+        }
+    }
+
+    fn abs() -> RAlgorithm<NanAfter> {
+        RAlgorithmBuilder::new()
+            .objective(NanAfter {
+                evaluations: Cell::new(0),
+                limit: usize::MAX,
+            })
+            .build()
+    }
+
+    #[test]
+    fn observer_stops_ralgo() {
+        let x = DVector::from_element(1, 10.0);
+
+        let result = abs()
+            .evaluate_with_observer(x.clone(), 0.01, &mut StopAt::iteration(true, 3))
+            .unwrap();
+        assert_eq!(result.termination, TerminationReason::Interrupted);
+        assert_eq!(result.iterations, 2);
+
+        let result = abs()
+            .evaluate_with_observer(x, 0.01, &mut StopAt::improvement(true))
+            .unwrap();
+        assert_eq!(result.termination, TerminationReason::Interrupted);
+        assert_eq!((result.iterations, result.evaluations), (1, 2));
+        assert!(result.f < 10.0);
+    }
+
+    #[test]
+    fn observer_stops_dichotomy() {
+        let x = DVector::from_element(1, 10.0);
+
+        let mut observer = StopAt::iteration(false, 2);
+        let result =
+            dichotomy_step_ralgo_with_observer(x.clone(), false, 1e-3, abs(), &mut observer)
+                .unwrap();
+        assert_eq!(result.termination, DichotomyTermination::Interrupted);
+        assert_eq!(observer.calls, 2);
+
+        let result = dichotomy_step_ralgo_with_observer(
+            x.clone(),
+            false,
+            1e-3,
+            abs(),
+            &mut StopAt::improvement(false),
+        )
+        .unwrap();
+        assert_eq!(result.termination, DichotomyTermination::Interrupted);
+        assert!(result.x[0].abs() < 10.0);
+
+        // Stopping the inner ralgo stops the dichotomy too, before any step is accepted.
+        let result = dichotomy_step_ralgo_with_observer(
+            x.clone(),
+            false,
+            1e-3,
+            abs(),
+            &mut StopAt::iteration(true, 1),
+        )
+        .unwrap();
+        assert_eq!(result.termination, DichotomyTermination::Interrupted);
+        assert_eq!(result.x, x);
+
+        let result = smart_dichotomy_step_ralgo_with_observer(
+            x,
+            1e-3,
+            abs(),
+            &mut StopAt::iteration(false, 2),
+        )
+        .unwrap();
+        assert_eq!(result.termination, DichotomyTermination::Interrupted);
+    }
+
+    #[test]
+    fn strip_length_is_refined() {
+        let shape = ContainerShape::Strip { width: 20.0 };
+        let packing = HeuristicAlgorithmBuilder::new()
+            .iterations(20)
+            .radiuses((1..=8).map(|radius| radius as FloatType).collect())
+            .max_small_circle_radius(2.0)
+            .container(shape)
+            .build()
+            .unwrap()
+            .find(None)
+            .packing
+            .unwrap();
+        let x = packing.into_coords_vec().unwrap();
+        let ralgo = RAlgorithmBuilder::new()
+            .objective(
+                CirclesPackingObjective::new(packing.inner_circles_radiuses()).with_shape(shape),
+            )
+            .build();
+
+        let y = dichotomy_step_ralgo(x.clone(), false, 1e-3, ralgo)
+            .unwrap()
+            .x;
+
+        assert!(last(&y) < last(&x), "{} vs {}", last(&y), last(&x));
+        let refined =
+            CirclesPacking::from_coords_vec(shape, y, &packing.inner_circles_radiuses()).unwrap();
+        assert!(refined.validate(1e-3).is_valid());
+    }
 }
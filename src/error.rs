@@ -0,0 +1,93 @@
+use std::{fmt, io};
+
+use crate::{circles_packing::PackingIoError, instance::InstanceError, utils::FloatType};
+
+/// Errors of the library, bad inputs are reported instead of panicking.
+#[derive(Debug)]
+pub enum Error {
+    /// Inner circle without a center where every circle has to be placed.
+    MissingCenter {
+        index: usize,
+    },
+    /// Radius which is not a positive finite number.
+    InvalidRadius {
+        index: usize,
+        radius: FloatType,
+    },
+    /// NaN or infinity in coordinates or in the penalty function value.
+    NotANumber,
+    EmptyInstance,
+    /// Number of variables does not match the number of circles.
+    DimensionMismatch {
+        expected: usize,
+        found: usize,
+    },
+    /// Builder parameter outside of its valid range.
+    InvalidParameter {
+        name: &'static str,
+        message: String,
+    },
+    Plot(String),
+    Io(io::Error),
+    Packing(PackingIoError),
+    Instance(InstanceError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MissingCenter { index } => write!(f, "circle {index} has no center"),
+            Error::InvalidRadius { index, radius } => {
+                write!(f, "radius of circle {index} must be positive, got {radius}")
+            }
+            Error::NotANumber => write!(f, "NaN encountered"),
+            Error::EmptyInstance => write!(f, "instance has no radiuses"),
+            Error::DimensionMismatch { expected, found } => {
+                write!(f, "expected {expected} variables, got {found}")
+            }
+            Error::InvalidParameter { name, message } => write!(f, "invalid {name}: {message}"),
+            Error::Plot(message) => write!(f, "plotting failed: {message}"),
+            Error::Io(err) => write!(f, "{err}"),
+            Error::Packing(err) => write!(f, "{err}"),
+            Error::Instance(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Packing(err) => Some(err),
+            Error::Instance(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<PackingIoError> for Error {
+    fn from(err: PackingIoError) -> Self {
+        Error::Packing(err)
+    }
+}
+
+impl From<InstanceError> for Error {
+    fn from(err: InstanceError) -> Self {
+        Error::Instance(err)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(err) => err,
+            err => io::Error::other(err),
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::cmp::Ordering;
+
+use crate::{
+    circle::Circle,
+    container::Container,
+    point::Point,
+    utils::{cast, Float, FloatType},
+};
+
+/// Slack for candidate positions lying exactly on the boundary of the allowed region.
+const TOLERANCE: FloatType = 1e-9;
+
+/// Order in which candidate positions are preferred.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Placement {
+    /// Lowest position first, then the leftmost.
+    BottomLeft,
+    /// Leftmost position first, then the lowest, fills a strip from left to right.
+    LeftBottom,
+}
+
+impl Placement {
+    fn key<T: Float>(self, point: &Point<T>) -> (&T, &T) {
+        match self {
+            Placement::BottomLeft => (&point.y, &point.x),
+            Placement::LeftBottom => (&point.x, &point.y),
+        }
+    }
+}
+
+/// Place circles one by one in the given order, every circle goes to the best by `placement`
+/// position touching two of the walls and already placed circles.
+///
+/// Circles which do not fit are skipped and left without center, `blockers` (fixed circles and
+/// obstacles) are avoided as already placed circles.
+pub(crate) fn pack_box<T: Float>(
+    radiuses: &[T],
+    container: &Container<T>,
+    delta: T,
+    placement: Placement,
+    blockers: &[Circle<T>],
+) -> Vec<Circle<T>> {
+    let (half_width, half_height) = container.half_extents();
+    let tolerance: T = cast(TOLERANCE);
+    let mut circles: Vec<Circle<T>> = Vec::with_capacity(radiuses.len());
+
+    for radius in radiuses {
+        let (max_x, max_y) = (
+            half_width.clone() - radius.clone() - delta.clone(),
+            half_height.clone() - radius.clone() - delta.clone(),
+        );
+        if max_x < T::zero() || max_y < T::zero() {
+            circles.push(Circle::with_radius(radius.clone()));
+            continue;
+        }
+
+        // Centers of placed circles with the distance the new center has to keep from them.
+        let obstacles: Vec<(Point<T>, T)> = circles
+            .iter()
+            .chain(blockers)
+            .filter_map(|c| {
+                Some((
+                    c.center.clone()?,
+                    c.radius.clone() + radius.clone() + delta.clone(),
+                ))
+            })
+            .collect();
+
+        let mut candidates: Vec<Point<T>> = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+            .into_iter()
+            .map(|(sx, sy)| Point {
+                x: cast::<T>(sx) * max_x.clone(),
+                y: cast::<T>(sy) * max_y.clone(),
+            })
+            .collect();
+
+        for (i, (center, distance)) in obstacles.iter().enumerate() {
+            for x in [-max_x.clone(), max_x.clone()] {
+                let dx = x.clone() - center.x.clone();
+                if dx.clone().abs() <= *distance {
+                    let dy = (distance.clone() * distance.clone() - dx.clone() * dx).sqrt();
+                    candidates.push(Point {
+                        x: x.clone(),
+                        y: center.y.clone() - dy.clone(),
+                    });
+                    candidates.push(Point {
+                        x,
+                        y: center.y.clone() + dy,
+                    });
+                }
+            }
+
+            for y in [-max_y.clone(), max_y.clone()] {
+                let dy = y.clone() - center.y.clone();
+                if dy.clone().abs() <= *distance {
+                    let dx = (distance.clone() * distance.clone() - dy.clone() * dy).sqrt();
+                    candidates.push(Point {
+                        x: center.x.clone() - dx.clone(),
+                        y: y.clone(),
+                    });
+                    candidates.push(Point {
+                        x: center.x.clone() + dx,
+                        y,
+                    });
+                }
+            }
+
+            for (other_center, other_distance) in &obstacles[i + 1..] {
+                candidates.extend(intersect(center, distance, other_center, other_distance));
+            }
+        }
+
+        candidates.sort_by(|a, b| {
+            let (a, b) = (placement.key(a), placement.key(b));
+            total_cmp(a.0, b.0).then(total_cmp(a.1, b.1))
+        });
+
+        let center = candidates.into_iter().find(|point| {
+            point.x.clone().abs() <= max_x.clone() + tolerance.clone()
+                && point.y.clone().abs() <= max_y.clone() + tolerance.clone()
+                && obstacles.iter().all(|(center, distance)| {
+                    (point.x.clone() - center.x.clone()).hypot(point.y.clone() - center.y.clone())
+                        >= distance.clone() - tolerance.clone()
+                })
+        });
+
+        circles.push(match center {
+            Some(center) => Circle::new(
+                radius.clone(),
+                Point {
+                    x: center.x.clamp(-max_x.clone(), max_x),
+                    y: center.y.clamp(-max_y.clone(), max_y),
+                },
+            ),
+            None => Circle::with_radius(radius.clone()),
+        });
+    }
+
+    circles
+}
+
+/// Intersection points of two circles.
+fn intersect<T: Float>(
+    first: &Point<T>,
+    first_radius: &T,
+    second: &Point<T>,
+    second_radius: &T,
+) -> Vec<Point<T>> {
+    let (dx, dy) = (
+        second.x.clone() - first.x.clone(),
+        second.y.clone() - first.y.clone(),
+    );
+    let distance = dx.clone().hypot(dy.clone());
+
+    if distance.is_zero()
+        || distance > first_radius.clone() + second_radius.clone()
+        || distance < (first_radius.clone() - second_radius.clone()).abs()
+    {
+        return Vec::new();
+    }
+
+    let a = (first_radius.clone().powi(2) - second_radius.clone().powi(2)
+        + distance.clone().powi(2))
+        / (cast::<T>(2.0) * distance.clone());
+    let h = (first_radius.clone().powi(2) - a.clone().powi(2))
+        .max(T::zero())
+        .sqrt();
+    let (mx, my) = (
+        first.x.clone() + a.clone() * dx.clone() / distance.clone(),
+        first.y.clone() + a * dy.clone() / distance.clone(),
+    );
+
+    vec![
+        Point {
+            x: mx.clone() - h.clone() * dy.clone() / distance.clone(),
+            y: my.clone() + h.clone() * dx.clone() / distance.clone(),
+        },
+        Point {
+            x: mx + h.clone() * dy / distance.clone(),
+            y: my - h * dx / distance,
+        },
+    ]
+}
+
+/// Total ordering of generic floats by their own comparison, NaN goes after every number.
+fn total_cmp<T: Float>(a: &T, b: &T) -> Ordering {
+    let is_nan = |value: &T| value.partial_cmp(value).is_none();
+    a.partial_cmp(b)
+        .unwrap_or_else(|| is_nan(a).cmp(&is_nan(b)))
+}
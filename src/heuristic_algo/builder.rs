@@ -1,30 +1,61 @@
+use std::{
+    sync::{atomic::AtomicBool, Arc},
+    time::Instant,
+};
+
 use rand::{rngs::StdRng, SeedableRng};
 
-use crate::utils::FloatType;
+use crate::{
+    circle::Circle,
+    clearance::Clearance,
+    container::ContainerShape,
+    error::Error,
+    utils::{cast, check_radiuses, to_float_type, Float, FloatType},
+};
 
 use super::HeuristicAlgorithm;
 
-pub struct HeuristicAlgorithmBuilder {
+#[derive(Clone)]
+pub struct HeuristicAlgorithmBuilder<T: Float = FloatType> {
     iterations: usize,
     rng: StdRng,
-    radiuses: Vec<FloatType>,
-    max_small_circle_radius: FloatType,
-    delta: FloatType,
+    radiuses: Vec<T>,
+    max_small_circle_radius: T,
+    delta: T,
+    container: ContainerShape<T>,
+    max_unplaced: Option<usize>,
+    fixed_circles: Vec<Circle<T>>,
+    obstacles: Vec<Circle<T>>,
+    clearance: Clearance<T>,
+    deadline: Option<Instant>,
+    max_stall_iterations: Option<usize>,
+    target: Option<T>,
+    stop_flag: Option<Arc<AtomicBool>>,
 }
 
-impl Default for HeuristicAlgorithmBuilder {
+impl<T: Float> Default for HeuristicAlgorithmBuilder<T> {
     fn default() -> Self {
         Self {
             iterations: Default::default(),
             rng: StdRng::seed_from_u64(0),
             radiuses: Vec::new(),
             max_small_circle_radius: Default::default(),
-            delta: 1e-6,
+            // Gap between touching circles has to stay above the rounding error of `T`.
+            delta: cast::<T>(1e-6).max(T::default_epsilon().sqrt()),
+            container: ContainerShape::Circle,
+            max_unplaced: None,
+            fixed_circles: Vec::new(),
+            obstacles: Vec::new(),
+            clearance: Clearance::default(),
+            deadline: None,
+            max_stall_iterations: None,
+            target: None,
+            stop_flag: None,
         }
     }
 }
 
-impl HeuristicAlgorithmBuilder {
+impl<T: Float> HeuristicAlgorithmBuilder<T> {
     pub fn new() -> Self {
         Self::default()
     }
@@ -39,28 +70,320 @@ impl HeuristicAlgorithmBuilder {
         self
     }
 
-    pub fn radiuses(mut self, radiuses: Vec<FloatType>) -> Self {
+    pub fn radiuses(mut self, radiuses: Vec<T>) -> Self {
         self.radiuses = radiuses;
         self
     }
 
-    pub fn max_small_circle_radius(mut self, max_small_circle_radius: FloatType) -> Self {
+    pub fn max_small_circle_radius(mut self, max_small_circle_radius: T) -> Self {
         self.max_small_circle_radius = max_small_circle_radius;
         self
     }
 
-    pub fn delta(mut self, delta: FloatType) -> Self {
+    pub fn delta(mut self, delta: T) -> Self {
         self.delta = delta;
         self
     }
 
-    pub fn build(self) -> HeuristicAlgorithm {
-        HeuristicAlgorithm {
+    pub fn container(mut self, container: ContainerShape<T>) -> Self {
+        self.container = container;
+        self
+    }
+
+    /// Give up on the current order if more than `max_unplaced` circles do not fit into the
+    /// container just below the best found size.
+    pub fn max_unplaced(mut self, max_unplaced: Option<usize>) -> Self {
+        self.max_unplaced = max_unplaced;
+        self
+    }
+
+    /// Circles with dictated centers, they are added after `radiuses` to the found packings.
+    pub fn fixed_circles(mut self, fixed_circles: Vec<Circle<T>>) -> Self {
+        self.fixed_circles = fixed_circles;
+        self
+    }
+
+    /// Circular regions with centers circles must avoid.
+    pub fn obstacles(mut self, obstacles: Vec<Circle<T>>) -> Self {
+        self.obstacles = obstacles;
+        self
+    }
+
+    /// Gaps circles keep from each other, obstacles and the container wall.
+    pub fn clearance(mut self, clearance: Clearance<T>) -> Self {
+        self.clearance = clearance;
+        self
+    }
+
+    /// Stop as soon as `deadline` passes, the bisection in progress is cut short.
+    pub fn deadline(mut self, deadline: Option<Instant>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Stop after this many iterations in a row without improvement.
+    pub fn max_stall_iterations(mut self, max_stall_iterations: Option<usize>) -> Self {
+        self.max_stall_iterations = max_stall_iterations;
+        self
+    }
+
+    /// Stop as soon as the container size is not bigger than `target`, e.g. the best known
+    /// size from the literature.
+    pub fn target(mut self, target: Option<T>) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Stop as soon as the flag is set, the run sets it itself when it reaches the target, so
+    /// that other runs sharing the flag stop too.
+    pub fn stop_flag(mut self, stop_flag: Option<Arc<AtomicBool>>) -> Self {
+        self.stop_flag = stop_flag;
+        self
+    }
+
+    /// Fails on an empty or invalid list of radiuses, fixed circles or obstacles without a
+    /// center or with an invalid radius, zero iterations, a nonpositive delta, a rectangle or
+    /// strip narrower than the biggest circle and a `max_small_circle_radius` larger than every
+    /// radius.
+    ///
+    /// Fixed circles are numbered after `radiuses` in errors and obstacles after them.
+    pub fn build(self) -> Result<HeuristicAlgorithm<T>, Error> {
+        let algorithm = self.build_for_fixed_size()?;
+
+        if let ContainerShape::Rectangle { width } | ContainerShape::Strip { width } =
+            &algorithm.container
+        {
+            let max_radius = algorithm.radiuses.iter().cloned().fold(T::zero(), T::max);
+            let min_width = cast::<T>(2.0) * (max_radius + algorithm.clearance.wall.clone());
+            if *width < min_width {
+                return Err(Error::InvalidParameter {
+                    name: "width",
+                    message: format!(
+                        "must hold the biggest circle with the wall clearance, {}, got {}",
+                        to_float_type(min_width),
+                        to_float_type(width.clone())
+                    ),
+                });
+            }
+        }
+
+        Ok(algorithm)
+    }
+
+    /// [`build`](Self::build) for packing into a container of a fixed size, e.g. by the
+    /// knapsack algorithm, where a rectangle or strip only has to have a positive width and
+    /// circles wider than it are left out.
+    pub(crate) fn build_for_fixed_size(self) -> Result<HeuristicAlgorithm<T>, Error> {
+        check_radiuses(&self.radiuses)?;
+        check_placed(&self.fixed_circles, self.radiuses.len())?;
+        check_placed(
+            &self.obstacles,
+            self.radiuses.len() + self.fixed_circles.len(),
+        )?;
+
+        if self.iterations == 0 {
+            return Err(Error::InvalidParameter {
+                name: "iterations",
+                message: "must be positive".to_string(),
+            });
+        }
+        if self.max_stall_iterations == Some(0) {
+            return Err(Error::InvalidParameter {
+                name: "max_stall_iterations",
+                message: "must be positive".to_string(),
+            });
+        }
+        if let Some(target) = self
+            .target
+            .clone()
+            .filter(|target| !(*target > T::zero() && target.is_finite()))
+        {
+            return Err(Error::InvalidParameter {
+                name: "target",
+                message: format!("must be positive, got {}", to_float_type(target)),
+            });
+        }
+        if !(self.delta > T::zero() && self.delta.is_finite()) {
+            return Err(Error::InvalidParameter {
+                name: "delta",
+                message: format!(
+                    "must be positive, got {}",
+                    to_float_type(self.delta.clone())
+                ),
+            });
+        }
+
+        if let ContainerShape::Rectangle { width } | ContainerShape::Strip { width } =
+            &self.container
+        {
+            if !(*width > T::zero() && width.is_finite()) {
+                return Err(Error::InvalidParameter {
+                    name: "width",
+                    message: format!("must be positive, got {}", to_float_type(width.clone())),
+                });
+            }
+        }
+
+        let max_radius = self.radiuses.iter().cloned().fold(T::zero(), T::max);
+        if !(self.max_small_circle_radius >= T::zero()
+            && self.max_small_circle_radius <= max_radius)
+        {
+            return Err(Error::InvalidParameter {
+                name: "max_small_circle_radius",
+                message: format!(
+                    "must be between 0 and the biggest radius {}, got {}",
+                    to_float_type(max_radius),
+                    to_float_type(self.max_small_circle_radius)
+                ),
+            });
+        }
+
+        Ok(HeuristicAlgorithm {
             iterations: self.iterations,
             rng: self.rng,
+            indexes: (0..self.radiuses.len()).collect(),
             radiuses: self.radiuses,
             max_small_circle_radius: self.max_small_circle_radius,
             delta: self.delta,
+            container: self.container,
+            max_unplaced: self.max_unplaced,
+            fixed_circles: self.fixed_circles,
+            obstacles: self.obstacles,
+            clearance: self.clearance,
+            deadline: self.deadline,
+            max_stall_iterations: self.max_stall_iterations,
+            target: self.target,
+            stop_flag: self.stop_flag,
+        })
+    }
+}
+
+/// Every circle must have a center and a valid radius, `first_index` is the number of the
+/// first one in errors.
+fn check_placed<T: Float>(circles: &[Circle<T>], first_index: usize) -> Result<(), Error> {
+    if let Some(i) = circles.iter().position(|circle| circle.center.is_none()) {
+        return Err(Error::MissingCenter {
+            index: first_index + i,
+        });
+    }
+
+    let radiuses: Vec<T> = circles.iter().map(|circle| circle.radius.clone()).collect();
+    match check_radiuses(&radiuses) {
+        Err(Error::InvalidRadius { index, radius }) => Err(Error::InvalidRadius {
+            index: first_index + index,
+            radius,
+        }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point::Point;
+
+    fn builder() -> HeuristicAlgorithmBuilder {
+        HeuristicAlgorithmBuilder::new()
+            .iterations(10)
+            .radiuses(vec![1.0, 2.0])
+            .max_small_circle_radius(1.0)
+    }
+
+    #[test]
+    fn invalid_radiuses_are_rejected() {
+        assert!(matches!(
+            builder().radiuses(Vec::new()).build(),
+            Err(Error::EmptyInstance)
+        ));
+        assert!(matches!(
+            builder().radiuses(vec![1.0, FloatType::NAN]).build(),
+            Err(Error::InvalidRadius { index: 1, .. })
+        ));
+        assert!(matches!(
+            builder().radiuses(vec![-1.0, 2.0]).build(),
+            Err(Error::InvalidRadius { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn invalid_parameters_are_rejected() {
+        let rejected = |builder: HeuristicAlgorithmBuilder| match builder.build() {
+            Err(Error::InvalidParameter { name, .. }) => name,
+            _ => panic!("invalid parameter is accepted"),
+        };
+
+        assert_eq!(rejected(builder().iterations(0)), "iterations");
+        assert_eq!(
+            rejected(builder().max_small_circle_radius(2.5)),
+            "max_small_circle_radius"
+        );
+        assert_eq!(rejected(builder().delta(0.0)), "delta");
+        assert_eq!(rejected(builder().target(Some(-1.0))), "target");
+        assert_eq!(
+            rejected(builder().max_stall_iterations(Some(0))),
+            "max_stall_iterations"
+        );
+        for width in [0.0, -1.0, 3.9, FloatType::NAN] {
+            assert_eq!(
+                rejected(builder().container(ContainerShape::Rectangle { width })),
+                "width"
+            );
+            assert_eq!(
+                rejected(builder().container(ContainerShape::Strip { width })),
+                "width"
+            );
         }
+        assert_eq!(
+            rejected(
+                builder()
+                    .container(ContainerShape::Strip { width: 4.0 })
+                    .clearance(Clearance::new(0.0, 0.5))
+            ),
+            "width"
+        );
+        assert!(builder()
+            .container(ContainerShape::Rectangle { width: 4.0 })
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn fixed_circles_and_obstacles_need_centers() {
+        let placed = Circle::new(1.0, Point { x: 5.0, y: 0.0 });
+
+        assert!(matches!(
+            builder()
+                .fixed_circles(vec![placed, Circle::with_radius(1.0)])
+                .build(),
+            Err(Error::MissingCenter { index: 3 })
+        ));
+        assert!(matches!(
+            builder()
+                .fixed_circles(vec![placed])
+                .obstacles(vec![Circle::with_radius(1.0)])
+                .build(),
+            Err(Error::MissingCenter { index: 3 })
+        ));
+        assert!(builder()
+            .fixed_circles(vec![placed])
+            .obstacles(vec![placed])
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn fixed_circles_and_obstacles_need_valid_radiuses() {
+        let placed = |radius| Circle::new(radius, Point { x: 5.0, y: 0.0 });
+
+        assert!(matches!(
+            builder().fixed_circles(vec![placed(0.0)]).build(),
+            Err(Error::InvalidRadius { index: 2, .. })
+        ));
+        assert!(matches!(
+            builder()
+                .obstacles(vec![placed(1.0), placed(FloatType::NAN)])
+                .build(),
+            Err(Error::InvalidRadius { index: 3, .. })
+        ));
     }
 }
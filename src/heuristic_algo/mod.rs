@@ -1,129 +1,304 @@
+mod box_packing;
 pub mod builder;
+pub mod partial_packing;
+pub mod result;
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
 use rand::rngs::StdRng;
 use rand::Rng;
 
 use crate::circle::*;
 use crate::circles_packing::CirclesPacking;
+use crate::clearance::Clearance;
+use crate::container::{Container, ContainerShape};
+use crate::observer::{NoopObserver, Observer, Progress, Signal, Stage};
 use crate::point::Point;
-use crate::utils::FloatType;
+use crate::utils::{cast, to_float_type, Float, FloatType};
+use crate::validation::ValidationReport;
 
+use self::box_packing::{pack_box, Placement};
 pub use self::builder::HeuristicAlgorithmBuilder;
+pub use self::partial_packing::PartialPacking;
+pub use self::result::{HeuristicResult, StopReason};
 
-pub struct HeuristicAlgorithm {
+pub struct HeuristicAlgorithm<T: Float = FloatType> {
     iterations: usize,
     rng: StdRng,
-    radiuses: Vec<FloatType>,
-    max_small_circle_radius: FloatType,
-    delta: FloatType,
+    radiuses: Vec<T>,
+    /// Original index of every radius in `radiuses`, swapped together with them.
+    indexes: Vec<usize>,
+    max_small_circle_radius: T,
+    delta: T,
+    container: ContainerShape<T>,
+    max_unplaced: Option<usize>,
+    /// Circles with dictated centers, placed after the movable ones.
+    fixed_circles: Vec<Circle<T>>,
+    obstacles: Vec<Circle<T>>,
+    clearance: Clearance<T>,
+    deadline: Option<Instant>,
+    max_stall_iterations: Option<usize>,
+    target: Option<T>,
+    stop_flag: Option<Arc<AtomicBool>>,
 }
 
-impl HeuristicAlgorithm {
-    pub fn find(&mut self, skip_iteration: Option<usize>) -> CirclesPacking {
+impl<T: Float> HeuristicAlgorithm<T> {
+    pub fn find(&mut self, skip_iteration: Option<usize>) -> HeuristicResult<T> {
+        self.find_with_observer(skip_iteration, &mut NoopObserver)
+    }
+
+    /// Runs until `iterations` are done, the deadline passes or the stop flag is set (checked
+    /// before every packing of the bisection), `max_stall_iterations` pass without improvement
+    /// or the size of a found packing reaches the target, which also sets the stop flag.
+    pub fn find_with_observer(
+        &mut self,
+        skip_iteration: Option<usize>,
+        observer: &mut dyn Observer,
+    ) -> HeuristicResult<T> {
+        let start_time = Instant::now();
+
         if let Some(skip_iteration) = skip_iteration {
             for _ in 0..skip_iteration {
                 self.swap_circles();
             }
         }
 
-        let radiuses_sum = (self.radiuses.iter().sum::<FloatType>() as FloatType).ceil();
+        let radiuses_sum = self
+            .radiuses
+            .iter()
+            .map(|radius| self.clearance.inflate(radius.clone()))
+            .fold(T::zero(), |sum, radius| sum + radius)
+            .ceil();
+
+        // Circles in a row always fit into a box with the side of their diameters sum.
+        let mut main_circle_radius: T = match self.container {
+            ContainerShape::Circle => radiuses_sum + self.clearance.wall.clone(),
+            ContainerShape::Square
+            | ContainerShape::Rectangle { .. }
+            | ContainerShape::Strip { .. } => {
+                cast::<T>(2.0) * (radiuses_sum + self.clearance.wall.clone())
+            }
+        };
+        // Movable circles are packed around fixed circles and obstacles.
+        main_circle_radius += self
+            .fixed_circles
+            .iter()
+            .chain(&self.obstacles)
+            .filter_map(|circle| {
+                let container = Container::new(self.container.clone(), T::zero());
+                Some(container.required_size(
+                    circle.center.clone()?,
+                    circle.radius.clone() + self.clearance.wall.clone(),
+                ))
+            })
+            .fold(T::zero(), T::max);
+
+        let mut new_innner_circles_pack: Vec<Circle<T>> = Vec::new();
+        let mut inner_circles_pack: Vec<Circle<T>> = Vec::new();
+        let mut inner_circles_indexes = self.original_indexes();
+        let (mut iterations, mut last_improvement) = (0, 0);
+        // Until the first improvement `main_circle_radius` is only an upper bound.
+        let mut found = false;
+
+        let stop_reason = 'search: {
+            for iter in 0..self.iterations {
+                if let Some(reason) = self.interruption() {
+                    break 'search reason;
+                }
+                if self
+                    .max_stall_iterations
+                    .is_some_and(|max_stall| iter - last_improvement >= max_stall)
+                {
+                    break 'search StopReason::Stall;
+                }
 
-        let mut main_circle_radius: FloatType = radiuses_sum;
+                let progress = Progress {
+                    stage: Stage::Heuristic,
+                    iteration: iter,
+                    best_value: to_float_type(main_circle_radius.clone()),
+                    elapsed: start_time.elapsed(),
+                };
+                if observer.on_iteration(&progress) == Signal::Stop {
+                    break 'search StopReason::Interrupted;
+                }
 
-        let mut new_innner_circles_pack: Vec<Circle> = Vec::new();
-        let mut inner_circles_pack: Vec<Circle> = (0..self.radiuses.len())
-            .map(|_| Circle::default())
-            .collect();
+                let (mut left, mut right) = (T::zero(), main_circle_radius.clone());
+                let mut interruption = None;
 
-        for iter in 0..self.iterations {
-            if iter % 10_000 == 0 {
-                println!("iter = {iter}");
-            }
+                while right.clone() - left.clone() >= cast(1e-4) {
+                    interruption = self.interruption();
+                    if interruption.is_some() {
+                        break;
+                    }
 
-            let (mut left, mut right) = (0 as FloatType, main_circle_radius);
+                    // let middle = (left + right) / 2.0;
+                    let middle = left.clone() + (right.clone() - left.clone()) / cast(1.02);
+                    // let middle = right - (right - left) / 500.0;
+
+                    let packing = self.pack_circles(middle.clone());
+                    if packing.is_complete() {
+                        right = middle;
+                        new_innner_circles_pack = packing.circles;
+                    } else if right == main_circle_radius
+                        && self
+                            .max_unplaced
+                            .is_some_and(|max_unplaced| packing.unplaced.len() > max_unplaced)
+                    {
+                        // Too many circles do not fit just below the best size, this order is
+                        // unlikely to improve it.
+                        break;
+                    } else {
+                        left = middle;
+                    }
+                }
 
-            while right - left >= 1e-4 {
-                // let middle = (left + right) / 2.0;
-                let middle = left + (right - left) / 1.02;
-                // let middle = right - (right - left) / 500.0;
+                iterations = iter + 1;
 
-                if let Some(circles) = self.pack_circles(middle) {
-                    right = middle;
-                    new_innner_circles_pack = circles;
-                } else {
-                    left = middle;
+                let new_main_circle_radius = right;
+                if new_main_circle_radius < main_circle_radius
+                    && is_valid_pack_with_clearance(
+                        &Container::new(self.container.clone(), new_main_circle_radius.clone()),
+                        &new_innner_circles_pack,
+                        &self.clearance,
+                    )
+                {
+                    main_circle_radius = new_main_circle_radius;
+                    inner_circles_pack = new_innner_circles_pack.clone();
+                    inner_circles_indexes = self.original_indexes();
+                    last_improvement = iter + 1;
+                    found = true;
+
+                    let progress = Progress {
+                        best_value: to_float_type(main_circle_radius.clone()),
+                        elapsed: start_time.elapsed(),
+                        ..progress
+                    };
+                    if observer.on_improvement(&progress) == Signal::Stop {
+                        break 'search StopReason::Interrupted;
+                    }
                 }
-            }
 
-            let new_main_circle_radius = right;
-            if new_main_circle_radius < main_circle_radius
-                && is_valid_pack(new_main_circle_radius, &new_innner_circles_pack)
-            {
-                main_circle_radius = new_main_circle_radius;
-                inner_circles_pack = new_innner_circles_pack.clone();
+                // Packing found before the interruption is kept, it is as valid as any other.
+                if let Some(reason) = interruption {
+                    break 'search reason;
+                }
+
+                if found
+                    && self
+                        .target
+                        .as_ref()
+                        .is_some_and(|target| main_circle_radius <= *target)
+                {
+                    if let Some(stop_flag) = &self.stop_flag {
+                        stop_flag.store(true, Ordering::Relaxed);
+                    }
+                    break 'search StopReason::Target;
+                }
+
+                self.swap_circles();
             }
 
-            self.swap_circles();
+            StopReason::MaxIterations
+        };
+
+        let (movable, fixed) = (self.radiuses.len(), self.fixed_circles.len());
+
+        let packing = found.then(|| {
+            CirclesPacking::new(
+                Container::new(self.container.clone(), main_circle_radius),
+                inner_circles_pack,
+            )
+            .with_original_indexes(inner_circles_indexes)
+            .with_fixed([vec![false; movable], vec![true; fixed]].concat())
+            .with_obstacles(self.obstacles.clone())
+            .with_clearance(self.clearance.clone())
+        });
+
+        HeuristicResult {
+            packing,
+            iterations,
+            stop_reason,
         }
+    }
 
-        CirclesPacking::new(
-            Circle::new(main_circle_radius, Point::default()),
-            inner_circles_pack,
-        )
+    /// Reason to stop the search right away, if the deadline passed or the stop flag is set.
+    fn interruption(&self) -> Option<StopReason> {
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            Some(StopReason::Deadline)
+        } else if self
+            .stop_flag
+            .as_ref()
+            .is_some_and(|stop_flag| stop_flag.load(Ordering::Relaxed))
+        {
+            Some(StopReason::Stopped)
+        } else {
+            None
+        }
     }
 
     fn pack_first_layer(
         &self,
-        circles: &mut [Circle],
+        circles: &mut [Circle<T>],
         placed_circle_indexes: &mut Vec<usize>,
-        main_circle_radius: FloatType,
+        main_circle_radius: T,
     ) {
-        let get_rotated_point = |y_coord: FloatType, angle: FloatType| -> Point {
+        let get_rotated_point = |y_coord: T, angle: T| -> Point<T> {
             Point {
-                x: y_coord * angle.sin(),
+                x: y_coord.clone() * angle.clone().sin(),
                 y: y_coord * angle.cos(),
             }
         };
 
-        let angle_between_prev_next_circles = |prev_radius: FloatType,
-                                               next_radius: FloatType,
-                                               main_circle_radius: FloatType|
-         -> FloatType {
-            let (a, b, c) = (
-                main_circle_radius - prev_radius - self.delta,
-                prev_radius + next_radius + self.delta,
-                main_circle_radius - next_radius - self.delta,
-            );
-
-            ((a.powi(2) + c.powi(2) - b.powi(2)) / (2.0 * a * c)).acos()
-        };
+        let two = cast::<T>(2.0);
+        let angle_between_prev_next_circles =
+            |prev_radius: T, next_radius: T, main_circle_radius: T| -> T {
+                let (a, b, c) = (
+                    main_circle_radius.clone() - prev_radius.clone() - self.delta.clone(),
+                    prev_radius + next_radius.clone() + self.delta.clone(),
+                    main_circle_radius - next_radius - self.delta.clone(),
+                );
 
-        circles[0].center = Some(Point {
-            x: 0.0,
-            y: main_circle_radius - self.delta - circles[0].radius,
-        });
+                ((a.clone().powi(2) + c.clone().powi(2) - b.powi(2)) / (two.clone() * a * c)).acos()
+            };
 
-        placed_circle_indexes.push(0);
-        let mut prev_circle_angle: FloatType = 0.0;
+        let mut prev_circle_angle = T::zero();
 
-        for index in 1..circles.len() {
-            if (1.0..=self.max_small_circle_radius).contains(&circles[index].radius) {
+        for index in 0..circles.len() {
+            if circles[index].center.is_some()
+                || (index > 0
+                    && (T::one()..=self.max_small_circle_radius.clone())
+                        .contains(&circles[index].radius))
+            {
                 continue;
             }
 
-            let next_circle_angle = prev_circle_angle
-                + angle_between_prev_next_circles(
-                    circles[placed_circle_indexes[placed_circle_indexes.len() - 1]].radius,
-                    circles[index].radius,
-                    main_circle_radius,
-                );
+            let next_circle_angle = match placed_circle_indexes.last() {
+                Some(&prev_index) => {
+                    prev_circle_angle.clone()
+                        + angle_between_prev_next_circles(
+                            circles[prev_index].radius.clone(),
+                            circles[index].radius.clone(),
+                            main_circle_radius.clone(),
+                        )
+                }
+                None => T::zero(),
+            };
 
             let new_circle = Circle {
                 center: Some(get_rotated_point(
-                    main_circle_radius - self.delta - circles[index].radius,
-                    next_circle_angle,
+                    main_circle_radius.clone() - self.delta.clone() - circles[index].radius.clone(),
+                    next_circle_angle.clone(),
                 )),
-                radius: circles[index].radius,
+                radius: circles[index].radius.clone(),
             };
 
             if !new_circle.is_overlap_quad(circles) {
@@ -138,53 +313,57 @@ impl HeuristicAlgorithm {
     /// Find tangent circle center to previous and main circles.
     fn find_tangent_circle_center(
         &self,
-        prev_circle: &Circle,
-        next_circle: &Circle,
-        main_circle_radius: FloatType,
-    ) -> Option<Vec<Point>> {
-        if let Some(prev_circle_center) = prev_circle.center {
+        prev_circle: &Circle<T>,
+        next_circle: &Circle<T>,
+        main_circle_radius: T,
+    ) -> Option<Vec<Point<T>>> {
+        let (two, four) = (cast::<T>(2.0), cast::<T>(4.0));
+
+        if let Some(prev_circle_center) = &prev_circle.center {
             let (xp, yp, rp) = (
-                prev_circle_center.x,
-                prev_circle_center.y,
-                prev_circle.radius,
+                prev_circle_center.x.clone(),
+                prev_circle_center.y.clone(),
+                prev_circle.radius.clone(),
             );
-            let rn = next_circle.radius;
+            let rn = next_circle.radius.clone();
+            // Squared distances from the next center to the previous one and to (0, 0).
+            let prev_distance = (rp + rn.clone() + self.delta.clone()).powi(2);
+            let main_distance = (main_circle_radius - self.delta.clone() - rn).powi(2);
 
-            let t = (xp.powi(2) + yp.powi(2)) - (rp + rn + self.delta).powi(2)
-                + (main_circle_radius - self.delta - rn).powi(2);
-            let p = 2.0 * yp;
+            let t = (xp.clone().powi(2) + yp.clone().powi(2)) - prev_distance.clone()
+                + main_distance.clone();
+            let p = two.clone() * yp.clone();
 
-            if yp == 0.0 {
-                if xp == 0.0 {
+            if yp.is_zero() {
+                if xp.is_zero() {
                     return None;
                 }
 
-                let xn = (xp.powi(2) - (rp + rn + self.delta).powi(2)
-                    + (main_circle_radius - self.delta - rn).powi(2))
-                    / (2.0 * xp);
+                let xn = (xp.clone().powi(2) - prev_distance.clone() + main_distance)
+                    / (two * xp.clone());
 
-                let yn = ((rp + rn + self.delta).powi(2) - (xp - xn).powi(2)).sqrt();
+                let yn = (prev_distance - (xp - xn.clone()).powi(2)).sqrt();
 
                 return Some(vec![Point { x: xn, y: yn }]);
             }
 
-            let a = p.powi(2) + 4.0 * xp.powi(2);
-            let b = -4.0 * xp * t;
-            let c = t.powi(2) - p.powi(2) * (main_circle_radius - self.delta - rn).powi(2);
+            let a = p.clone().powi(2) + four.clone() * xp.clone().powi(2);
+            let b = -four.clone() * xp.clone() * t.clone();
+            let c = t.clone().powi(2) - p.clone().powi(2) * main_distance;
 
-            let d = b.powi(2) - 4.0 * a * c;
+            let d = b.clone().powi(2) - four * a.clone() * c;
 
-            if d < 0.0 {
+            if d < T::zero() {
                 return None;
             }
 
             let sqrt_d = d.sqrt();
 
-            let xn_1 = (-b - sqrt_d) / (2.0 * a);
-            let xn_2 = (-b + sqrt_d) / (2.0 * a);
+            let xn_1 = (-b.clone() - sqrt_d.clone()) / (two.clone() * a.clone());
+            let xn_2 = (-b + sqrt_d) / (two.clone() * a);
 
-            let yn_1 = (t - 2.0 * xp * xn_1) / p;
-            let yn_2 = (t - 2.0 * xp * xn_2) / p;
+            let yn_1 = (t.clone() - two.clone() * xp.clone() * xn_1.clone()) / p.clone();
+            let yn_2 = (t - two * xp * xn_2.clone()) / p;
 
             Some(vec![Point { x: xn_1, y: yn_1 }, Point { x: xn_2, y: yn_2 }])
         } else {
@@ -194,14 +373,15 @@ impl HeuristicAlgorithm {
 
     fn pack_small_circle_layer(
         &self,
-        circles: &mut [Circle],
+        circles: &mut [Circle<T>],
         placed_circle_indexes: &Vec<usize>,
-        main_circle_radius: FloatType,
+        main_circle_radius: T,
     ) {
         for placed_circle_index in placed_circle_indexes {
             'circles_loop: for i in 0..circles.len() {
                 if circles[i].center.is_some()
-                    || !(1.0..=self.max_small_circle_radius).contains(&circles[i].radius)
+                    || !(T::one()..=self.max_small_circle_radius.clone())
+                        .contains(&circles[i].radius)
                 {
                     continue;
                 }
@@ -209,15 +389,15 @@ impl HeuristicAlgorithm {
                 if let Some(points) = self.find_tangent_circle_center(
                     &circles[*placed_circle_index],
                     &circles[i],
-                    main_circle_radius,
+                    main_circle_radius.clone(),
                 ) {
                     for point in points {
-                        let new_circle: Circle = Circle {
-                            radius: circles[i].radius,
+                        let new_circle: Circle<T> = Circle {
+                            radius: circles[i].radius.clone(),
                             center: Some(point),
                         };
 
-                        if new_circle.is_inside_main_circle_quad(main_circle_radius)
+                        if new_circle.is_inside_main_circle_quad(main_circle_radius.clone())
                             && !new_circle.is_overlap_quad(circles)
                         {
                             circles[i] = new_circle;
@@ -230,56 +410,67 @@ impl HeuristicAlgorithm {
     }
 
     /// Find center of third circle, closest to (0, 0).
-    fn find_third_circle_center(&self, c1: &Circle, c2: &Circle, r3: FloatType) -> Option<Point> {
-        if let (Some(c1_center), Some(c2_center)) = (c1.center, c2.center) {
-            if (c1_center.x - c2_center.x).powi(2) + (c1_center.y - c2_center.y).powi(2)
-                > (c1.radius + 2.0 * r3 + c2.radius).powi(2)
+    fn find_third_circle_center(&self, c1: &Circle<T>, c2: &Circle<T>, r3: T) -> Option<Point<T>> {
+        let (two, four) = (cast::<T>(2.0), cast::<T>(4.0));
+
+        if let (Some(c1_center), Some(c2_center)) = (&c1.center, &c2.center) {
+            let (x1, y1, r1) = (c1_center.x.clone(), c1_center.y.clone(), c1.radius.clone());
+            let (x2, y2, r2) = (c2_center.x.clone(), c2_center.y.clone(), c2.radius.clone());
+
+            if (x1.clone() - x2.clone()).powi(2) + (y1.clone() - y2.clone()).powi(2)
+                > (r1.clone() + two.clone() * r3.clone() + r2.clone()).powi(2)
             {
                 return None;
             }
 
-            let (x1, y1, r1) = (c1_center.x, c1_center.y, c1.radius);
-            let (x2, y2, r2) = (c2_center.x, c2_center.y, c2.radius);
-
-            if (y1 - y2).abs() == 0.0 {
-                if (x1 - x2).abs() == 0.0 {
+            if (y1.clone() - y2.clone()).abs().is_zero() {
+                if (x1.clone() - x2.clone()).abs().is_zero() {
                     return None;
                 }
 
-                let x_3 = ((x1.powi(2) - x2.powi(2)) - (r1 + r3).powi(2) + (r2 + r3).powi(2))
-                    / (2.0 * (x1 - x2));
+                let x_3 = ((x1.clone().powi(2) - x2.clone().powi(2))
+                    - (r1.clone() + r3.clone()).powi(2)
+                    + (r2 + r3.clone()).powi(2))
+                    / (two * (x1.clone() - x2));
 
-                let y_3 = ((x1 - x_3).powi(2) - (r1 + r3).powi(2)).sqrt() + y1;
+                let y_3 = ((x1 - x_3.clone()).powi(2) - (r1 + r3).powi(2)).sqrt() + y1;
 
                 return Some(Point { x: x_3, y: y_3 });
             }
 
-            let t =
-                x1.powi(2) - x2.powi(2) + y1.powi(2) - y2.powi(2) - (r1 + r3 + self.delta).powi(2)
-                    + (r2 + r3 + self.delta).powi(2);
-            let p = 2.0 * (y1 - y2);
+            let t = x1.clone().powi(2) - x2.clone().powi(2) + y1.clone().powi(2)
+                - y2.clone().powi(2)
+                - (r1.clone() + r3.clone() + self.delta.clone()).powi(2)
+                + (r2 + r3.clone() + self.delta.clone()).powi(2);
+            let p = two.clone() * (y1.clone() - y2);
+            let dx = x1.clone() - x2;
 
-            let a = p.powi(2) + 4.0 * (x1 - x2).powi(2);
+            let a = p.clone().powi(2) + four.clone() * dx.clone().powi(2);
 
-            let b = -2.0 * x1 * p.powi(2) + 4.0 * (y1 * p - t) * (x1 - x2);
-            let c = p.powi(2) * (x1.powi(2) + y1.powi(2) - (r1 + r3 + self.delta).powi(2))
-                - 2.0 * y1 * p * t
-                + t.powi(2);
+            let b = -two.clone() * x1.clone() * p.clone().powi(2)
+                + four.clone() * (y1.clone() * p.clone() - t.clone()) * dx.clone();
+            let c = p.clone().powi(2)
+                * (x1.clone().powi(2) + y1.clone().powi(2)
+                    - (r1 + r3 + self.delta.clone()).powi(2))
+                - two.clone() * y1 * p.clone() * t.clone()
+                + t.clone().powi(2);
 
-            let d = b.powi(2) - 4.0 * a * c;
+            let d = b.clone().powi(2) - four * a.clone() * c;
 
-            if d < 0.0 {
+            if d < T::zero() {
                 return None;
             }
 
             let sqrt_d = d.sqrt();
-            let x3_1 = (-b - sqrt_d) / (2.0 * a);
-            let x3_2 = (-b + sqrt_d) / (2.0 * a);
+            let x3_1 = (-b.clone() - sqrt_d.clone()) / (two.clone() * a.clone());
+            let x3_2 = (-b + sqrt_d) / (two.clone() * a);
 
-            let y3_1 = (t - 2.0 * (x1 - x2) * x3_1) / p;
-            let y3_2 = (t - 2.0 * (x1 - x2) * x3_2) / p;
+            let y3_1 = (t.clone() - two.clone() * dx.clone() * x3_1.clone()) / p.clone();
+            let y3_2 = (t - two * dx * x3_2.clone()) / p;
 
-            let c3_center = if x3_1.powi(2) + y3_1.powi(2) < x3_2.powi(2) + y3_2.powi(2) {
+            let c3_center = if x3_1.clone().powi(2) + y3_1.clone().powi(2)
+                < x3_2.clone().powi(2) + y3_2.clone().powi(2)
+            {
                 Point { x: x3_1, y: y3_1 }
             } else {
                 Point { x: x3_2, y: y3_2 }
@@ -291,19 +482,20 @@ impl HeuristicAlgorithm {
         }
     }
 
-    fn eval_alpha(&self, c1: &Circle, c2: &Circle, r3: FloatType) -> FloatType {
-        let a = c1.radius + r3 + self.delta;
-        let b = c2.radius + r3 + self.delta;
-        let c = c1.distance(c2) + self.delta;
+    fn eval_alpha(&self, c1: &Circle<T>, c2: &Circle<T>, r3: T) -> T {
+        let a = c1.radius.clone() + r3.clone() + self.delta.clone();
+        let b = c2.radius.clone() + r3 + self.delta.clone();
+        let c = c1.distance(c2) + self.delta.clone();
+        let two = cast::<T>(2.0);
 
-        (a * a + b * b - c * c) / (2.0 * a * b)
+        (a.clone() * a.clone() + b.clone() * b.clone() - c.clone() * c) / (two * a * b)
     }
 
     fn pack_other_layers(
         &self,
-        circles: &mut [Circle],
+        circles: &mut [Circle<T>],
         mut placed_circle_indexes: Vec<usize>,
-        main_circle_radius: FloatType,
+        main_circle_radius: T,
     ) {
         let cycle_index =
             |vector: &Vec<usize>, index: usize| -> usize { vector[index % vector.len()] };
@@ -313,7 +505,7 @@ impl HeuristicAlgorithm {
 
             for placed_circle_index in 0..placed_circle_indexes.len() {
                 let (mut candidate_alpha, mut candidate_circle_index, mut candidate_circle) =
-                    (FloatType::NEG_INFINITY, 0, Circle::default());
+                    (cast(FloatType::NEG_INFINITY), 0, Circle::default());
                 for i in 0..circles.len() {
                     if circles[i].center.is_some() {
                         continue;
@@ -327,29 +519,29 @@ impl HeuristicAlgorithm {
                         let alpha = self.eval_alpha(
                             &circles[first_index],
                             &circles[second_index],
-                            circles[i].radius,
+                            circles[i].radius.clone(),
                         );
 
                         if alpha <= candidate_alpha {
                             continue;
                         }
 
-                        let new_circle_center: Option<Point> = self.find_third_circle_center(
+                        let new_circle_center: Option<Point<T>> = self.find_third_circle_center(
                             &circles[first_index],
                             &circles[second_index],
-                            circles[i].radius,
+                            circles[i].radius.clone(),
                         );
 
                         if new_circle_center.is_none() {
                             continue;
                         }
 
-                        let new_circle: Circle = Circle {
+                        let new_circle: Circle<T> = Circle {
                             center: new_circle_center,
-                            radius: circles[i].radius,
+                            radius: circles[i].radius.clone(),
                         };
 
-                        if new_circle.is_inside_main_circle_quad(main_circle_radius)
+                        if new_circle.is_inside_main_circle_quad(main_circle_radius.clone())
                             && !new_circle.is_overlap_quad(circles)
                         {
                             (candidate_alpha, candidate_circle_index, candidate_circle) =
@@ -358,7 +550,7 @@ impl HeuristicAlgorithm {
                     }
                 }
 
-                if candidate_alpha != FloatType::NEG_INFINITY {
+                if candidate_alpha != cast(FloatType::NEG_INFINITY) {
                     circles[candidate_circle_index] = candidate_circle;
                     new_placed_circle_indexes.push(candidate_circle_index);
                 }
@@ -367,48 +559,178 @@ impl HeuristicAlgorithm {
         }
     }
 
-    fn pack_circles(&self, main_circle_radius: FloatType) -> Option<Vec<Circle>> {
-        let mut circles: Vec<Circle> = self
+    /// Place circles in the current order into the container of the given size.
+    ///
+    /// Circles sticking out of the container or overlapping already placed ones are left
+    /// without center, see [`sort_out`](Self::sort_out).
+    pub fn pack_circles(&self, size: T) -> PartialPacking<T> {
+        self.sort_out(size.clone(), self.place_circles(size))
+    }
+
+    /// Split placed movable circles followed by the fixed ones into fitting and unplaced.
+    ///
+    /// Fixed circles are checked first, only against the container. A movable circle
+    /// overlapping a fixed one, an obstacle or an earlier movable circle is the one left
+    /// without center.
+    fn sort_out(&self, size: T, circles: Vec<Circle<T>>) -> PartialPacking<T> {
+        let container = Container::new(self.container.clone(), size);
+        let mut packing = PartialPacking {
+            circles,
+            original_indexes: self.original_indexes(),
+            ..Default::default()
+        };
+
+        let movable = self.radiuses.len();
+        for i in (movable..packing.circles.len()).chain(0..movable) {
+            let circle = &packing.circles[i];
+            let fits = container.contains_with_clearance(circle, self.clearance.wall.clone())
+                && (i >= movable
+                    || !packing.placed.iter().any(|&j| {
+                        circle.overlap_with_clearance(
+                            &packing.circles[j],
+                            self.clearance.pair.clone(),
+                        )
+                    }) && !circle
+                        .is_overlap_with_clearance(&self.obstacles, self.clearance.pair.clone()));
+
+            if fits {
+                packing.placed.push(i);
+            } else {
+                packing.circles[i].center = None;
+                packing.unplaced.push(i);
+            }
+        }
+        packing.placed.sort_unstable();
+        packing.unplaced.sort_unstable();
+
+        packing
+    }
+
+    /// Movable circles in the current order followed by the fixed ones.
+    ///
+    /// Circles grown by half of the pair clearance are placed touching each other, see
+    /// [`Clearance::inflate_container`].
+    fn place_circles(&self, size: T) -> Vec<Circle<T>> {
+        let container = self
+            .clearance
+            .inflate_container(&Container::new(self.container.clone(), size));
+        let radiuses: Vec<T> = self
             .radiuses
             .iter()
-            .map(|&radius| Circle::with_radius(radius))
+            .map(|radius| self.clearance.inflate(radius.clone()))
+            .collect();
+        let blockers: Vec<Circle<T>> = self
+            .fixed_circles
+            .iter()
+            .chain(&self.obstacles)
+            .map(|circle| Circle {
+                center: circle.center.clone(),
+                radius: self.clearance.inflate(circle.radius.clone()),
+            })
+            .collect();
+
+        let mut circles = match self.container {
+            ContainerShape::Circle => {
+                self.pack_circles_into_circle(&radiuses, &blockers, container.size.clone())
+            }
+            ContainerShape::Square | ContainerShape::Rectangle { .. } => pack_box(
+                &radiuses,
+                &container,
+                self.delta.clone(),
+                Placement::BottomLeft,
+                &blockers,
+            ),
+            ContainerShape::Strip { .. } => pack_box(
+                &radiuses,
+                &container,
+                self.delta.clone(),
+                Placement::LeftBottom,
+                &blockers,
+            ),
+        };
+        for (circle, radius) in circles.iter_mut().zip(&self.radiuses) {
+            circle.radius = radius.clone();
+        }
+        circles.extend_from_slice(&self.fixed_circles);
+
+        circles
+    }
+
+    fn pack_circles_into_circle(
+        &self,
+        radiuses: &[T],
+        blockers: &[Circle<T>],
+        main_circle_radius: T,
+    ) -> Vec<Circle<T>> {
+        // Fixed circles and obstacles already have centers, so they are treated as placed ones:
+        // the first layer avoids them and the other layers are packed around them too.
+        let mut circles: Vec<Circle<T>> = radiuses
+            .iter()
+            .map(|radius| Circle::with_radius(radius.clone()))
+            .chain(blockers.iter().cloned())
             .collect();
 
         let mut placed_circle_indexes: Vec<usize> = Vec::new();
 
-        self.pack_first_layer(&mut circles, &mut placed_circle_indexes, main_circle_radius);
+        self.pack_first_layer(
+            &mut circles,
+            &mut placed_circle_indexes,
+            main_circle_radius.clone(),
+        );
 
-        self.pack_small_circle_layer(&mut circles, &placed_circle_indexes, main_circle_radius);
+        self.pack_small_circle_layer(
+            &mut circles,
+            &placed_circle_indexes,
+            main_circle_radius.clone(),
+        );
 
+        placed_circle_indexes.extend(radiuses.len()..circles.len());
         self.pack_other_layers(&mut circles, placed_circle_indexes, main_circle_radius);
 
-        if is_valid_pack(main_circle_radius, &circles) {
-            Some(circles)
-        } else {
-            None
-        }
+        circles.truncate(radiuses.len());
+        circles
     }
 
-    fn swap_circles(&mut self) {
+    /// Original index of every movable circle in the current order, followed by the fixed ones.
+    fn original_indexes(&self) -> Vec<usize> {
+        let movable = self.radiuses.len();
+        self.indexes
+            .iter()
+            .copied()
+            .chain(movable..movable + self.fixed_circles.len())
+            .collect()
+    }
+
+    pub(crate) fn swap_circles(&mut self) {
         let circles_number = self.radiuses.len();
-        self.radiuses.swap(
+        let (i, j) = (
             self.rng.gen_range(0..circles_number),
             self.rng.gen_range(0..circles_number),
         );
+        self.radiuses.swap(i, j);
+        self.indexes.swap(i, j);
     }
 }
 
-pub fn is_valid_pack(main_circle_radius: FloatType, circles: &[Circle]) -> bool {
+pub fn is_valid_pack<T: Float>(container: &Container<T>, circles: &[Circle<T>]) -> bool {
+    is_valid_pack_with_clearance(container, circles, &Clearance::default())
+}
+
+pub fn is_valid_pack_with_clearance<T: Float>(
+    container: &Container<T>,
+    circles: &[Circle<T>],
+    clearance: &Clearance<T>,
+) -> bool {
     if circles
         .iter()
-        .any(|circle| !circle.is_inside_main_circle(main_circle_radius))
+        .any(|circle| !container.contains_with_clearance(circle, clearance.wall.clone()))
     {
         return false;
     }
 
     for i in 0..circles.len() {
         for j in i + 1..circles.len() {
-            if circles[i].overlap(&circles[j]) {
+            if circles[i].overlap_with_clearance(&circles[j], clearance.pair.clone()) {
                 return false;
             }
         }
@@ -416,3 +738,225 @@ pub fn is_valid_pack(main_circle_radius: FloatType, circles: &[Circle]) -> bool
 
     true
 }
+
+pub fn validate_pack<T: Float>(
+    container: &Container<T>,
+    circles: &[Circle<T>],
+    tolerance: T,
+) -> ValidationReport<T> {
+    ValidationReport::new(container, circles, tolerance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder() -> HeuristicAlgorithmBuilder {
+        HeuristicAlgorithmBuilder::new()
+            .iterations(100)
+            .radiuses((1..=8).map(|radius| radius as FloatType).collect())
+            .max_small_circle_radius(2.0)
+    }
+
+    #[test]
+    fn reaching_target_stops_runs_sharing_the_flag() {
+        let stop_flag = Arc::new(AtomicBool::new(false));
+
+        let mut first = builder()
+            .target(Some(1000.0))
+            .stop_flag(Some(stop_flag.clone()))
+            .build()
+            .unwrap();
+        let result = first.find(None);
+        assert_eq!(result.stop_reason, StopReason::Target);
+        assert!(stop_flag.load(Ordering::Relaxed));
+
+        let mut second = builder().stop_flag(Some(stop_flag)).build().unwrap();
+        let result = second.find(None);
+        assert_eq!(result.stop_reason, StopReason::Stopped);
+        assert_eq!(result.iterations, 0);
+    }
+
+    #[test]
+    fn deadline_before_first_packing_gives_no_packing() {
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let mut algorithm = builder()
+            .deadline(Some(Instant::now()))
+            .target(Some(1000.0))
+            .stop_flag(Some(stop_flag.clone()))
+            .build()
+            .unwrap();
+
+        let result = algorithm.find(None);
+
+        assert_eq!(result.stop_reason, StopReason::Deadline);
+        assert_eq!(result.iterations, 0);
+        assert!(result.packing.is_none());
+        assert!(!stop_flag.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn stall_stops_search() {
+        let mut algorithm = builder()
+            .iterations(1000)
+            .max_stall_iterations(Some(5))
+            .build()
+            .unwrap();
+
+        let result = algorithm.find(None);
+
+        assert_eq!(result.stop_reason, StopReason::Stall);
+        assert!(result.iterations < 1000);
+        assert!(result.packing.unwrap().is_valid_pack());
+    }
+
+    #[test]
+    fn box_containers_get_valid_packings() {
+        for container in [
+            ContainerShape::Square,
+            ContainerShape::Rectangle { width: 20.0 },
+            ContainerShape::Strip { width: 20.0 },
+        ] {
+            let packing = builder()
+                .iterations(20)
+                .container(container)
+                .build()
+                .unwrap()
+                .find(None)
+                .packing
+                .unwrap();
+
+            assert_eq!(packing.container.shape, container);
+            assert!(packing.is_valid_pack());
+            assert!(packing.validate(0.0).is_valid());
+        }
+    }
+
+    #[test]
+    fn packings_keep_clearances() {
+        for container in [
+            ContainerShape::Circle,
+            ContainerShape::Square,
+            ContainerShape::Strip { width: 20.0 },
+        ] {
+            let packing = builder()
+                .iterations(20)
+                .container(container)
+                .clearance(Clearance::new(0.5, 1.0))
+                .build()
+                .unwrap()
+                .find(None)
+                .packing
+                .unwrap();
+
+            assert_eq!(packing.clearance, Clearance::new(0.5, 1.0));
+            let report = packing.validate(0.0);
+            assert!(report.is_valid(), "{container:?}: {report}");
+        }
+    }
+
+    #[test]
+    fn strip_is_filled_from_left_to_right() {
+        let packing = builder()
+            .iterations(20)
+            .container(ContainerShape::Strip { width: 20.0 })
+            .build()
+            .unwrap()
+            .find(None)
+            .packing
+            .unwrap();
+        let half_length = packing.container.size / 2.0;
+        let centers = packing.centers().unwrap();
+        let (left, right) = packing.inner_circles.iter().zip(&centers).fold(
+            (FloatType::INFINITY, FloatType::NEG_INFINITY),
+            |(left, right), (circle, center)| {
+                (
+                    left.min(center.x - circle.radius),
+                    right.max(center.x + circle.radius),
+                )
+            },
+        );
+
+        assert!(packing.validate(0.0).is_valid());
+        // Circles start at the left end and the length is cut right after the last one.
+        assert!(left <= -half_length + 1e-3, "{left} vs {}", -half_length);
+        assert!(right >= half_length - 1e-3, "{right} vs {half_length}");
+    }
+
+    #[test]
+    fn partial_packing_keeps_original_indexes() {
+        let radiuses: Vec<FloatType> = (1..=8).map(|radius| radius as FloatType).collect();
+        let fixed = Circle::new(1.5, Point { x: 0.0, y: 0.0 });
+        let mut algorithm = builder().fixed_circles(vec![fixed]).build().unwrap();
+        for _ in 0..10 {
+            algorithm.swap_circles();
+        }
+
+        let packing = algorithm.pack_circles(12.0);
+        assert_eq!(packing.original_indexes.len(), radiuses.len() + 1);
+        for (circle, &index) in packing.circles.iter().zip(&packing.original_indexes) {
+            let radius = radiuses.get(index).unwrap_or(&fixed.radius);
+            assert_eq!(circle.radius, *radius);
+        }
+
+        let mut indexes = [packing.placed_indexes(), packing.unplaced_indexes()].concat();
+        indexes.sort_unstable();
+        assert_eq!(indexes, (0..=radiuses.len()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn movable_circle_overlapping_fixed_one_is_unplaced() {
+        let fixed = Circle::new(1.0, Point { x: 0.0, y: 0.0 });
+        let obstacle = Circle::new(1.0, Point { x: 0.0, y: 5.0 });
+        let algorithm = builder()
+            .radiuses(vec![1.0, 1.0, 1.0])
+            .max_small_circle_radius(1.0)
+            .fixed_circles(vec![fixed])
+            .obstacles(vec![obstacle])
+            .build()
+            .unwrap();
+        let circles = vec![
+            Circle::new(1.0, Point { x: 1.0, y: 0.0 }),
+            Circle::new(1.0, Point { x: 0.0, y: 4.0 }),
+            Circle::new(1.0, Point { x: -5.0, y: 0.0 }),
+            fixed,
+        ];
+
+        let packing = algorithm.sort_out(10.0, circles);
+
+        assert_eq!(packing.placed, [2, 3]);
+        assert_eq!(packing.unplaced, [0, 1]);
+        assert!(packing.circles[3].center.is_some());
+    }
+
+    #[test]
+    fn placements_avoid_fixed_circles_and_obstacles() {
+        let fixed = Circle::new(3.0, Point { x: 2.0, y: 1.0 });
+        let obstacles = vec![
+            Circle::new(2.5, Point { x: -4.0, y: -3.0 }),
+            Circle::new(1.5, Point { x: 5.0, y: -6.0 }),
+        ];
+
+        for container in [ContainerShape::Circle, ContainerShape::Square] {
+            let packing = builder()
+                .iterations(20)
+                .container(container)
+                .fixed_circles(vec![fixed])
+                .obstacles(obstacles.clone())
+                .build()
+                .unwrap()
+                .find(None)
+                .packing
+                .unwrap();
+
+            let report = packing.validate(0.0);
+            assert!(
+                report.obstacle_overlaps.is_empty(),
+                "{container:?}: {report}"
+            );
+            assert!(report.is_valid(), "{container:?}: {report}");
+            let center = packing.inner_circles.last().unwrap().center.unwrap();
+            assert_eq!((center.x, center.y), (2.0, 1.0));
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{
+    circle::Circle,
+    utils::{Float, FloatType},
+};
+
+/// Circles placed into the container of a fixed size, some of them may not fit.
+#[derive(Clone, Debug, Default)]
+pub struct PartialPacking<T: Float = FloatType> {
+    /// Circles in the current heuristic order, unplaced ones have no center.
+    pub circles: Vec<Circle<T>>,
+    /// Original index of every circle in `circles`, fixed circles follow the movable ones.
+    pub original_indexes: Vec<usize>,
+    /// Positions in `circles` of the circles inside the container, not overlapping each other.
+    pub placed: Vec<usize>,
+    /// Positions in `circles` of the circles which did not fit.
+    pub unplaced: Vec<usize>,
+}
+
+impl<T: Float> PartialPacking<T> {
+    pub fn is_complete(&self) -> bool {
+        self.unplaced.is_empty()
+    }
+
+    /// Original indexes of the circles inside the container.
+    pub fn placed_indexes(&self) -> Vec<usize> {
+        self.placed
+            .iter()
+            .map(|&i| self.original_indexes[i])
+            .collect()
+    }
+
+    /// Original indexes of the circles which did not fit.
+    pub fn unplaced_indexes(&self) -> Vec<usize> {
+        self.unplaced
+            .iter()
+            .map(|&i| self.original_indexes[i])
+            .collect()
+    }
+
+    pub fn into_placed_circles(self) -> Vec<Circle<T>> {
+        self.circles
+            .into_iter()
+            .filter(|circle| circle.center.is_some())
+            .collect()
+    }
+}
@@ -0,0 +1,28 @@
+use crate::{
+    circles_packing::CirclesPacking,
+    utils::{Float, FloatType},
+};
+
+/// Why [`HeuristicAlgorithm::find`](super::HeuristicAlgorithm::find) stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    MaxIterations,
+    /// Deadline passed.
+    Deadline,
+    /// Too many iterations in a row did not improve the packing.
+    Stall,
+    /// Container size reached the target.
+    Target,
+    /// Observer requested to stop.
+    Interrupted,
+    /// Stop flag was set, e.g. by another run sharing it which reached the target.
+    Stopped,
+}
+
+pub struct HeuristicResult<T: Float = FloatType> {
+    /// Best found packing, `None` if the search stopped before finding any.
+    pub packing: Option<CirclesPacking<T>>,
+    /// Number of finished iterations.
+    pub iterations: usize,
+    pub stop_reason: StopReason,
+}
@@ -0,0 +1,418 @@
+use std::{collections::HashSet, fmt, fs, io, path::Path};
+
+use serde::Deserialize;
+
+use crate::{circle::Circle, point::Point, utils::FloatType};
+
+/// Set of inner circles radiuses to pack with optional metadata.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Instance {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub known_best_radius: Option<FloatType>,
+    #[serde(alias = "radii")]
+    pub radiuses: Vec<FloatType>,
+    /// Id of every radius from the id column of a csv instance, empty for other formats.
+    #[serde(skip)]
+    pub ids: Vec<String>,
+    /// Circles with dictated positions, packed together with `radiuses` but never moved.
+    #[serde(default)]
+    pub fixed: Vec<PlacedCircle>,
+    /// Regions inner circles must not intersect.
+    #[serde(default)]
+    pub obstacles: Vec<PlacedCircle>,
+}
+
+/// Circle with a known center, used for fixed circles and obstacles.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct PlacedCircle {
+    pub radius: FloatType,
+    pub x: FloatType,
+    pub y: FloatType,
+}
+
+impl From<PlacedCircle> for Circle {
+    fn from(circle: PlacedCircle) -> Self {
+        Circle::new(
+            circle.radius,
+            Point {
+                x: circle.x,
+                y: circle.y,
+            },
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InstanceFormat {
+    /// One radius per line.
+    Text,
+    /// `id,radius` rows with an optional header.
+    Csv,
+    /// Object with `name`, `known_best_radius`, `radiuses`, `fixed` and `obstacles` fields.
+    Json,
+}
+
+impl InstanceFormat {
+    /// Guess format by file extension, plain text is used for unknown extensions.
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("csv") => InstanceFormat::Csv,
+            Some("json") => InstanceFormat::Json,
+            _ => InstanceFormat::Text,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum InstanceError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Parse {
+        line: usize,
+        message: String,
+    },
+    InvalidRadius {
+        line: usize,
+        radius: FloatType,
+    },
+    /// Radius of a json instance circle, `list` is `radiuses`, `fixed` or `obstacles`.
+    InvalidCircleRadius {
+        list: &'static str,
+        index: usize,
+        radius: FloatType,
+    },
+    Empty,
+}
+
+impl fmt::Display for InstanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstanceError::Io(err) => write!(f, "{err}"),
+            InstanceError::Json(err) => write!(f, "invalid json instance: {err}"),
+            InstanceError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            InstanceError::InvalidRadius { line, radius } => {
+                write!(f, "line {line}: radius must be positive, got {radius}")
+            }
+            InstanceError::InvalidCircleRadius {
+                list,
+                index,
+                radius,
+            } => write!(
+                f,
+                "radius of circle {index} in `{list}` must be positive, got {radius}"
+            ),
+            InstanceError::Empty => write!(f, "instance has no radiuses"),
+        }
+    }
+}
+
+impl std::error::Error for InstanceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InstanceError::Io(err) => Some(err),
+            InstanceError::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for InstanceError {
+    fn from(err: io::Error) -> Self {
+        InstanceError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for InstanceError {
+    fn from(err: serde_json::Error) -> Self {
+        InstanceError::Json(err)
+    }
+}
+
+impl Instance {
+    pub fn new(radiuses: Vec<FloatType>) -> Self {
+        Self {
+            radiuses,
+            ..Default::default()
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, InstanceError> {
+        let content = fs::read_to_string(path)?;
+        let mut instance = Self::parse(&content, InstanceFormat::from_path(path))?;
+
+        if instance.name.is_none() {
+            instance.name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned());
+        }
+
+        Ok(instance)
+    }
+
+    pub fn parse(content: &str, format: InstanceFormat) -> Result<Self, InstanceError> {
+        match format {
+            InstanceFormat::Text => Self::from_text(content),
+            InstanceFormat::Csv => Self::from_csv(content),
+            InstanceFormat::Json => Self::from_json(content),
+        }
+    }
+
+    /// Parse one radius per line, blank lines and lines starting with `#` are skipped.
+    pub fn from_text(content: &str) -> Result<Self, InstanceError> {
+        let mut radiuses = Vec::new();
+
+        for (line, text) in data_lines(content) {
+            radiuses.push(parse_radius(text, line)?);
+        }
+
+        Self::new(radiuses).validated()
+    }
+
+    /// Parse `id,radius` rows, the header is optional and may swap the columns.
+    pub fn from_csv(content: &str) -> Result<Self, InstanceError> {
+        let mut lines = data_lines(content).peekable();
+        let (mut id_column, mut radius_column) = (0, 1);
+
+        if let Some((_, header)) = lines.peek() {
+            let columns: Vec<String> = header
+                .split(',')
+                .map(|column| column.trim().to_ascii_lowercase())
+                .collect();
+
+            if let Some(position) = columns
+                .iter()
+                .position(|column| column == "radius" || column == "r")
+            {
+                radius_column = position;
+                id_column = columns
+                    .iter()
+                    .position(|column| column == "id")
+                    .unwrap_or(if position == 0 { 1 } else { 0 });
+                lines.next();
+            }
+        }
+
+        let mut seen_ids = HashSet::new();
+        let mut ids = Vec::new();
+        let mut radiuses = Vec::new();
+
+        for (line, text) in lines {
+            let cells: Vec<&str> = text.split(',').map(str::trim).collect();
+            if cells.len() < 2 {
+                return Err(InstanceError::Parse {
+                    line,
+                    message: format!("expected `id,radius`, got `{text}`"),
+                });
+            }
+
+            let id = cells.get(id_column).copied().unwrap_or_default();
+            if !seen_ids.insert(id) {
+                return Err(InstanceError::Parse {
+                    line,
+                    message: format!("duplicate id `{id}`"),
+                });
+            }
+
+            let radius = cells
+                .get(radius_column)
+                .ok_or_else(|| InstanceError::Parse {
+                    line,
+                    message: "missing radius column".to_string(),
+                })?;
+
+            ids.push(id.to_string());
+            radiuses.push(parse_radius(radius, line)?);
+        }
+
+        Self {
+            ids,
+            ..Self::new(radiuses)
+        }
+        .validated()
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, InstanceError> {
+        let instance: Self = serde_json::from_str(content)?;
+
+        instance.validated()
+    }
+
+    fn validated(self) -> Result<Self, InstanceError> {
+        if self.radiuses.is_empty() {
+            return Err(InstanceError::Empty);
+        }
+
+        let lists = [
+            ("radiuses", self.radiuses.clone()),
+            ("fixed", self.fixed.iter().map(|c| c.radius).collect()),
+            (
+                "obstacles",
+                self.obstacles.iter().map(|c| c.radius).collect(),
+            ),
+        ];
+        for (list, radiuses) in lists {
+            if let Some(index) = radiuses.iter().position(|&r| !is_valid_radius(r)) {
+                return Err(InstanceError::InvalidCircleRadius {
+                    list,
+                    index,
+                    radius: radiuses[index],
+                });
+            }
+        }
+
+        Ok(self)
+    }
+}
+
+fn is_valid_radius(radius: FloatType) -> bool {
+    radius.is_finite() && radius > 0.0
+}
+
+fn data_lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(index, text)| (index + 1, text.trim()))
+        .filter(|(_, text)| !text.is_empty() && !text.starts_with('#'))
+}
+
+fn parse_radius(text: &str, line: usize) -> Result<FloatType, InstanceError> {
+    let radius = text
+        .parse::<FloatType>()
+        .map_err(|err| InstanceError::Parse {
+            line,
+            message: format!("invalid radius `{text}`: {err}"),
+        })?;
+
+    if !is_valid_radius(radius) {
+        return Err(InstanceError::InvalidRadius { line, radius });
+    }
+
+    Ok(radius)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malformed_line_reports_its_number() {
+        assert!(matches!(
+            Instance::from_text("1\n\n# comment\nabc\n"),
+            Err(InstanceError::Parse { line: 4, .. })
+        ));
+        assert!(matches!(
+            Instance::from_csv("a,1\nb\n"),
+            Err(InstanceError::Parse { line: 2, .. })
+        ));
+        assert!(matches!(
+            Instance::from_csv("a,1\nb,x\n"),
+            Err(InstanceError::Parse { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn invalid_radiuses_are_rejected() {
+        for radius in ["0", "-2", "NaN"] {
+            assert!(matches!(
+                Instance::from_text(&format!("1\n{radius}\n")),
+                Err(InstanceError::InvalidRadius { line: 2, .. })
+            ));
+        }
+        assert!(matches!(
+            Instance::from_csv("id,radius\na,1\nb,0\n"),
+            Err(InstanceError::InvalidRadius { line: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn empty_instance_is_rejected() {
+        assert!(matches!(Instance::from_text(""), Err(InstanceError::Empty)));
+        assert!(matches!(
+            Instance::from_csv("id,radius\n# no rows\n"),
+            Err(InstanceError::Empty)
+        ));
+        assert!(matches!(
+            Instance::from_json(r#"{ "radiuses": [] }"#),
+            Err(InstanceError::Empty)
+        ));
+    }
+
+    #[test]
+    fn csv_header_may_swap_columns() {
+        let instance = Instance::from_csv("radius,id\n2,a\n3,b\n").unwrap();
+        assert_eq!(instance.radiuses, vec![2.0, 3.0]);
+        assert_eq!(instance.ids, vec!["a", "b"]);
+
+        let instance = Instance::from_csv("a,2\nb,3\n").unwrap();
+        assert_eq!(instance.radiuses, vec![2.0, 3.0]);
+        assert_eq!(instance.ids, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn duplicate_csv_ids_are_rejected() {
+        assert!(matches!(
+            Instance::from_csv("id,radius\na,1\nb,2\na,3\n"),
+            Err(InstanceError::Parse { line: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn json_keeps_metadata() {
+        let instance = Instance::from_json(
+            r#"{
+                "name": "r_i = i, N = 2",
+                "known_best_radius": 3.0,
+                "radii": [1, 2],
+                "fixed": [{ "radius": 0.5, "x": 1, "y": 0 }],
+                "obstacles": [{ "radius": 0.25, "x": 0, "y": 1 }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(instance.name.as_deref(), Some("r_i = i, N = 2"));
+        assert_eq!(instance.known_best_radius, Some(3.0));
+        assert_eq!(instance.radiuses, vec![1.0, 2.0]);
+        assert_eq!(
+            instance.fixed,
+            vec![PlacedCircle {
+                radius: 0.5,
+                x: 1.0,
+                y: 0.0
+            }]
+        );
+        assert_eq!(instance.obstacles.len(), 1);
+    }
+
+    #[test]
+    fn json_invalid_radius_reports_its_circle() {
+        assert!(matches!(
+            Instance::from_json(r#"{ "radiuses": [1, -1] }"#),
+            Err(InstanceError::InvalidCircleRadius {
+                list: "radiuses",
+                index: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            Instance::from_json(
+                r#"{ "radiuses": [1], "obstacles": [
+                    { "radius": 1, "x": 0, "y": 0 },
+                    { "radius": 0, "x": 5, "y": 0 }
+                ] }"#
+            ),
+            Err(InstanceError::InvalidCircleRadius {
+                list: "obstacles",
+                index: 1,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,236 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    container::{Container, ContainerShape},
+    error::Error,
+    heuristic_algo::HeuristicAlgorithmBuilder,
+    utils::{check_radiuses, FloatType},
+};
+
+use super::{KnapsackAlgorithm, KnapsackGoal};
+
+pub struct KnapsackAlgorithmBuilder {
+    iterations: usize,
+    rng: StdRng,
+    radiuses: Vec<FloatType>,
+    values: Vec<FloatType>,
+    goal: KnapsackGoal,
+    container: Container,
+    max_small_circle_radius: FloatType,
+    delta: FloatType,
+}
+
+impl Default for KnapsackAlgorithmBuilder {
+    fn default() -> Self {
+        Self {
+            iterations: Default::default(),
+            rng: StdRng::seed_from_u64(0),
+            radiuses: Vec::new(),
+            values: Vec::new(),
+            goal: KnapsackGoal::default(),
+            container: Container::circle(1.0),
+            max_small_circle_radius: Default::default(),
+            delta: 1e-6,
+        }
+    }
+}
+
+impl KnapsackAlgorithmBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    pub fn rng(mut self, rng: StdRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Radiuses of the candidate circles.
+    pub fn radiuses(mut self, radiuses: Vec<FloatType>) -> Self {
+        self.radiuses = radiuses;
+        self
+    }
+
+    /// Value of every candidate circle, every circle is worth 1 by default.
+    pub fn values(mut self, values: Vec<FloatType>) -> Self {
+        self.values = values;
+        self
+    }
+
+    pub fn goal(mut self, goal: KnapsackGoal) -> Self {
+        self.goal = goal;
+        self
+    }
+
+    /// Container of the fixed size.
+    pub fn container(mut self, container: Container) -> Self {
+        self.container = container;
+        self
+    }
+
+    pub fn max_small_circle_radius(mut self, max_small_circle_radius: FloatType) -> Self {
+        self.max_small_circle_radius = max_small_circle_radius;
+        self
+    }
+
+    pub fn delta(mut self, delta: FloatType) -> Self {
+        self.delta = delta;
+        self
+    }
+
+    /// Candidates start ordered by radius for [`KnapsackGoal::Count`] and by value per area
+    /// for [`KnapsackGoal::Value`].
+    ///
+    /// Fails on invalid radiuses, negative or not finite values and a container size or width
+    /// which is not a positive finite number, besides the heuristic algorithm parameters.
+    pub fn build(self) -> Result<KnapsackAlgorithm, Error> {
+        check_radiuses(&self.radiuses)?;
+
+        if let Some(index) = self
+            .values
+            .iter()
+            .position(|value| !(*value >= 0.0 && value.is_finite()))
+        {
+            return Err(Error::InvalidParameter {
+                name: "values",
+                message: format!(
+                    "value of circle {index} must be nonnegative, got {}",
+                    self.values[index]
+                ),
+            });
+        }
+        if !(self.container.size > 0.0 && self.container.size.is_finite()) {
+            return Err(Error::InvalidParameter {
+                name: "container",
+                message: format!("size must be positive, got {}", self.container.size),
+            });
+        }
+        if let ContainerShape::Rectangle { width } | ContainerShape::Strip { width } =
+            self.container.shape
+        {
+            if !(width > 0.0 && width.is_finite()) {
+                return Err(Error::InvalidParameter {
+                    name: "container",
+                    message: format!("width must be positive, got {width}"),
+                });
+            }
+        }
+
+        let values = if self.values.is_empty() {
+            vec![1.0; self.radiuses.len()]
+        } else if self.values.len() != self.radiuses.len() {
+            return Err(Error::InvalidParameter {
+                name: "values",
+                message: format!(
+                    "every candidate circle must have a value, expected {} values, got {}",
+                    self.radiuses.len(),
+                    self.values.len()
+                ),
+            });
+        } else {
+            self.values
+        };
+
+        let mut order: Vec<usize> = (0..self.radiuses.len()).collect();
+        match self.goal {
+            KnapsackGoal::Count => {
+                order.sort_by(|&a, &b| self.radiuses[a].total_cmp(&self.radiuses[b]))
+            }
+            KnapsackGoal::Value => order.sort_by(|&a, &b| {
+                let density = |i: usize| values[i] / self.radiuses[i].powi(2);
+                density(b).total_cmp(&density(a))
+            }),
+        }
+
+        let heuristic = HeuristicAlgorithmBuilder::new()
+            .iterations(self.iterations)
+            .rng(self.rng)
+            .radiuses(order.iter().map(|&i| self.radiuses[i]).collect())
+            .max_small_circle_radius(self.max_small_circle_radius)
+            .delta(self.delta)
+            .container(self.container.shape)
+            .build_for_fixed_size()?;
+
+        Ok(KnapsackAlgorithm {
+            heuristic,
+            order,
+            iterations: self.iterations,
+            container: self.container,
+            values,
+            goal: self.goal,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder() -> KnapsackAlgorithmBuilder {
+        KnapsackAlgorithmBuilder::new()
+            .iterations(10)
+            .radiuses(vec![1.0, 2.0])
+            .max_small_circle_radius(1.0)
+            .container(Container::circle(3.0))
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        for value in [-1.0, FloatType::NAN, FloatType::INFINITY] {
+            assert!(matches!(
+                builder().values(vec![1.0, value]).build(),
+                Err(Error::InvalidParameter { name: "values", .. })
+            ));
+        }
+        assert!(builder().values(vec![0.0, 2.0]).build().is_ok());
+    }
+
+    #[test]
+    fn invalid_container_size_is_rejected() {
+        for size in [0.0, -3.0, FloatType::NAN, FloatType::INFINITY] {
+            assert!(matches!(
+                builder().container(Container::circle(size)).build(),
+                Err(Error::InvalidParameter {
+                    name: "container",
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn invalid_container_width_is_rejected() {
+        for width in [0.0, -3.0, FloatType::NAN, FloatType::INFINITY] {
+            for container in [
+                Container::rectangle(width, 6.0),
+                Container::strip(width, 6.0),
+            ] {
+                assert!(matches!(
+                    builder().container(container).build(),
+                    Err(Error::InvalidParameter {
+                        name: "container",
+                        ..
+                    })
+                ));
+            }
+        }
+        // Candidates wider than the container are left out, not rejected.
+        assert!(builder()
+            .container(Container::rectangle(3.0, 6.0))
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn invalid_radiuses_are_rejected() {
+        assert!(matches!(
+            builder().radiuses(vec![1.0, -2.0]).build(),
+            Err(Error::InvalidRadius { index: 1, .. })
+        ));
+    }
+}
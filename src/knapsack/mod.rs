@@ -0,0 +1,158 @@
+pub mod builder;
+
+use std::time::Instant;
+
+use crate::circle::Circle;
+use crate::circles_packing::CirclesPacking;
+use crate::container::Container;
+use crate::heuristic_algo::HeuristicAlgorithm;
+use crate::observer::{NoopObserver, Observer, Progress, Signal, Stage};
+use crate::utils::FloatType;
+
+pub use self::builder::KnapsackAlgorithmBuilder;
+
+/// What is maximised by the knapsack algorithm.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KnapsackGoal {
+    /// Number of placed circles.
+    #[default]
+    Count,
+    /// Total value of placed circles.
+    Value,
+}
+
+pub struct KnapsackSolution {
+    /// Placed circles only, original indexes point to the candidate circles.
+    pub packing: CirclesPacking,
+    /// Number or total value of placed circles, depending on the goal.
+    pub value: FloatType,
+}
+
+/// Chooses and places a subset of candidate circles into the container of a fixed size.
+///
+/// Every iteration places circles in the current order with the heuristic algorithm, keeping
+/// those which fit, and then swaps two random circles.
+pub struct KnapsackAlgorithm {
+    heuristic: HeuristicAlgorithm,
+    /// Candidate index of every circle given to the heuristic algorithm.
+    order: Vec<usize>,
+    iterations: usize,
+    container: Container,
+    values: Vec<FloatType>,
+    goal: KnapsackGoal,
+}
+
+impl KnapsackAlgorithm {
+    pub fn find(&mut self) -> KnapsackSolution {
+        self.find_with_observer(&mut NoopObserver)
+    }
+
+    pub fn find_with_observer(&mut self, observer: &mut dyn Observer) -> KnapsackSolution {
+        let start_time = Instant::now();
+
+        let mut best_value = 0.0;
+        let mut best_circles: Vec<Circle> = Vec::new();
+        let mut best_indexes: Vec<usize> = Vec::new();
+
+        for iter in 0..self.iterations {
+            let progress = Progress {
+                stage: Stage::Knapsack,
+                iteration: iter,
+                best_value,
+                elapsed: start_time.elapsed(),
+            };
+            if observer.on_iteration(&progress) == Signal::Stop {
+                break;
+            }
+
+            let (circles, indexes) = self.place();
+            let value = self.total_value(&indexes);
+
+            if value > best_value {
+                (best_value, best_circles, best_indexes) = (value, circles, indexes);
+
+                let progress = Progress {
+                    best_value,
+                    elapsed: start_time.elapsed(),
+                    ..progress
+                };
+                if observer.on_improvement(&progress) == Signal::Stop {
+                    break;
+                }
+            }
+
+            self.heuristic.swap_circles();
+        }
+
+        KnapsackSolution {
+            packing: CirclesPacking::new(self.container, best_circles)
+                .with_original_indexes(best_indexes),
+            value: best_value,
+        }
+    }
+
+    /// Place circles in the current order, returning placed circles with their candidate indexes.
+    fn place(&self) -> (Vec<Circle>, Vec<usize>) {
+        let packing = self.heuristic.pack_circles(self.container.size);
+
+        let indexes = packing
+            .placed_indexes()
+            .into_iter()
+            .map(|index| self.order[index])
+            .collect();
+
+        (packing.into_placed_circles(), indexes)
+    }
+
+    fn total_value(&self, indexes: &[usize]) -> FloatType {
+        match self.goal {
+            KnapsackGoal::Count => indexes.len() as FloatType,
+            KnapsackGoal::Value => indexes.iter().map(|&index| self.values[index]).sum(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three small circles fit together, a small one fits next to the big one only touching it,
+    /// which the heuristic gap does not allow.
+    fn solve(goal: KnapsackGoal) -> KnapsackSolution {
+        KnapsackAlgorithmBuilder::new()
+            .iterations(20)
+            .radiuses(vec![1.0, 2.0, 1.0, 1.0])
+            .values(vec![1.0, 10.0, 1.0, 1.0])
+            .goal(goal)
+            .max_small_circle_radius(0.5)
+            .container(Container::circle(3.0))
+            .build()
+            .unwrap()
+            .find()
+    }
+
+    fn sorted_indexes(solution: &KnapsackSolution) -> Vec<usize> {
+        let mut indexes = solution.packing.original_indexes.clone();
+        indexes.sort_unstable();
+        indexes
+    }
+
+    #[test]
+    fn count_goal_places_most_circles() {
+        let solution = solve(KnapsackGoal::Count);
+
+        assert!(solution.packing.is_valid_pack());
+        assert_eq!(solution.value, 3.0);
+        assert_eq!(sorted_indexes(&solution), vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn value_goal_prefers_valuable_circle() {
+        let solution = solve(KnapsackGoal::Value);
+
+        assert!(solution.packing.is_valid_pack());
+        assert_eq!(solution.value, 10.0);
+        assert_eq!(sorted_indexes(&solution), vec![1]);
+        assert_eq!(solution.packing.inner_circles_radiuses(), vec![2.0]);
+    }
+}
@@ -1,8 +1,23 @@
+pub mod balance;
+pub mod calcfg;
 pub mod circle;
 pub mod circles_packing;
+pub mod clearance;
+pub mod container;
 pub mod dichotomy_step_ralgo;
-pub mod calcfg;
+pub mod error;
 pub mod heuristic_algo;
-pub mod ralgo;
+pub mod instance;
+pub mod knapsack;
+pub mod multi_start;
+pub mod neighbour_list;
+#[cfg(test)]
+mod non_copy_float;
+pub mod observer;
 pub mod point;
-pub mod utils;
\ No newline at end of file
+pub mod ralgo;
+pub mod repair;
+pub mod utils;
+pub mod validation;
+
+pub use self::error::Error;
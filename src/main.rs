@@ -1,151 +1,791 @@
-use std::sync::Arc;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
 use circles_pack::{
-    calcfg::calcfg,
-    circles_packing::CirclesPacking,
-    dichotomy_step_ralgo::{dichotomy_step_ralgo, smart_dichotomy_step_ralgo},
-    heuristic_algo::HeuristicAlgorithmBuilder,
-    ralgo::RAlgorithmBuilder,
+    balance::{recentre, Balance},
+    calcfg::CirclesPackingObjective,
+    circle::Circle,
+    circles_packing::{CircleFill, CircleLabel, CirclesPacking, PlotOptions},
+    clearance::Clearance,
+    container::{Container, ContainerShape},
+    dichotomy_step_ralgo::{
+        dichotomy_step_ralgo_with_observer, smart_dichotomy_step_ralgo_with_observer,
+        DichotomyTermination,
+    },
+    heuristic_algo::{HeuristicAlgorithmBuilder, HeuristicResult},
+    instance::Instance,
+    knapsack::{KnapsackAlgorithmBuilder, KnapsackGoal},
+    multi_start::MultiStart,
+    observer::PrintObserver,
+    ralgo::{Dilation, RAlgorithmBuilder},
+    repair::repair,
     utils::{measure_time, FloatType},
 };
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use nalgebra::DVector;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rand::{rngs::StdRng, SeedableRng};
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Packing circles into a circle, square or rectangle of the minimum size"
+)]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    solve: SolveArgs,
+}
 
-fn main() {
-    let total_iterations: usize = 1_000_000;
-    let threads_number: usize = 10;
-    rayon::ThreadPoolBuilder::new()
-        .num_threads(threads_number)
-        .build_global()
-        .unwrap();
-
-    let test_number = 40;
-    let radiuses = (1..=test_number)
-        .map(|x| x as FloatType)
-        .collect::<Vec<_>>();
-
-    let (total_time, best) = measure_time(|| {
-        let par_iter = (1..=threads_number).into_par_iter().map(|x| {
-            let thread_iterations = total_iterations / threads_number;
-            let skip_iterations = (x - 1) * thread_iterations;
-
-            let mut heuristic_algo = HeuristicAlgorithmBuilder::new()
-                .iterations(thread_iterations)
-                .radiuses(radiuses.clone())
-                .max_small_circle_radius(
-                    radiuses
-                        .iter()
-                        .max_by(|a, b| a.partial_cmp(b).unwrap())
-                        .unwrap()
-                        * 0.3,
-                )
-                .delta(1e-6)
-                .build();
-
-            heuristic_algo.find(Some(skip_iterations))
-        });
-
-        let answers: Vec<_> = par_iter.collect();
-
-        let best = answers
-            .into_iter()
-            .filter(|a| a.is_valid_pack())
-            .min_by(|a, b| {
-                a.main_circle
-                    .radius
-                    .partial_cmp(&b.main_circle.radius)
-                    .unwrap()
-            })
-            .unwrap();
-
-        best
-    });
+#[derive(Subcommand)]
+enum Command {
+    /// Run the multi-threaded heuristic search only
+    Heuristic(HeuristicCommandArgs),
+    /// Refine a saved packing with the dichotomy step ralgo
+    Refine(RefineArgs),
+    /// Place as many (or as valuable) circles as possible into a container of the fixed size
+    Knapsack(KnapsackArgs),
+    /// Run the heuristic search and refine its best packing (default)
+    Solve(SolveArgs),
+}
 
-    println!("iters = {total_iterations}");
+#[derive(Args)]
+struct RadiusesArgs {
+    /// Comma separated radiuses of the inner circles
+    #[arg(long, value_delimiter = ',', conflicts_with_all = ["radiuses_file", "test_number"])]
+    radiuses: Option<Vec<FloatType>>,
 
-    println!("heuristic");
-    println!("time = {total_time}");
-    println!("R = {}", best.main_circle.radius);
-    best.plot("images/heuristic.png");
+    /// Instance file: one radius per line, `id,radius` csv or json with metadata
+    #[arg(long, conflicts_with = "test_number")]
+    radiuses_file: Option<PathBuf>,
 
-    println!();
+    /// Use the test case r_i = i, i = 1, ..., N
+    #[arg(long, default_value_t = 40)]
+    test_number: usize,
+}
+
+#[derive(Args)]
+struct HeuristicArgs {
+    /// Total number of heuristic iterations shared between workers
+    #[arg(long, default_value_t = 1_000_000)]
+    iterations: usize,
+
+    /// Number of threads running the heuristic search
+    #[arg(long, default_value_t = 10)]
+    threads: usize,
+
+    /// Number of independent heuristic searches, `--threads` by default. Results depend only
+    /// on `--seed` and the number of workers, unless `--time-limit` or `--target` stops them
+    #[arg(long)]
+    workers: Option<usize>,
+
+    /// Master seed of the random orders of all workers
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Circles not bigger than this part of the biggest radius are packed as small ones
+    #[arg(long, default_value_t = 0.3)]
+    small_circle_ratio: FloatType,
+
+    #[arg(long, default_value_t = 1e-6)]
+    delta: FloatType,
+
+    #[command(flatten)]
+    container: ContainerArgs,
+
+    /// Stop the heuristic search after this many seconds
+    #[arg(long)]
+    time_limit: Option<FloatType>,
+
+    /// Stop a worker after this many iterations in a row without improvement
+    #[arg(long)]
+    max_stall: Option<usize>,
+
+    /// Stop every worker as soon as one of them gets the container size not bigger than this,
+    /// e.g. the best known size
+    #[arg(long)]
+    target: Option<FloatType>,
+
+    /// Skip the rest of the bisection when more than this many circles do not fit just below
+    /// the best size (faster, slightly worse packings)
+    #[arg(long)]
+    max_unplaced: Option<usize>,
+
+    /// Minimum gap between every two circles and between circles and obstacles
+    #[arg(long, default_value_t = 0.0)]
+    clearance: FloatType,
+
+    /// Minimum gap between circles and the container wall
+    #[arg(long, default_value_t = 0.0)]
+    wall_clearance: FloatType,
+}
+
+#[derive(Args)]
+struct ContainerArgs {
+    /// Shape of the container, its radius, side, height or length is minimised
+    #[arg(long, value_enum, default_value_t = Shape::Circle)]
+    container: Shape,
+
+    /// Fixed width of the rectangle or strip container
+    #[arg(long, required_if_eq_any([("container", "rectangle"), ("container", "strip")]))]
+    width: Option<FloatType>,
+}
+
+impl ContainerArgs {
+    fn shape(&self) -> ContainerShape {
+        match self.container {
+            Shape::Circle => ContainerShape::Circle,
+            Shape::Square => ContainerShape::Square,
+            Shape::Rectangle => ContainerShape::Rectangle {
+                width: self.width.expect("width is required for a rectangle"),
+            },
+            Shape::Strip => ContainerShape::Strip {
+                width: self.width.expect("width is required for a strip"),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Shape {
+    Circle,
+    Square,
+    Rectangle,
+    Strip,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Dichotomy {
+    /// Halve the step until the radius stops decreasing
+    Plain,
+    /// Reset the step to the initial one after every improvement
+    Reset,
+    /// Double the step after every improvement
+    Smart,
+}
+
+impl Dichotomy {
+    fn suffix(&self) -> &'static str {
+        match self {
+            Dichotomy::Plain => "",
+            Dichotomy::Reset => " (r)",
+            Dichotomy::Smart => " (s)",
+        }
+    }
+}
+
+#[derive(Args)]
+struct RAlgorithmArgs {
+    #[arg(long, default_value_t = 1.5)]
+    alpha: FloatType,
+
+    /// Comma separated q1 values, every value is refined separately
+    #[arg(long, value_delimiter = ',', default_values_t = [0.95, 1.0])]
+    q1: Vec<FloatType>,
+
+    #[arg(long, default_value_t = 1e-6)]
+    epsx: FloatType,
+
+    #[arg(long, default_value_t = 1e-7)]
+    epsg: FloatType,
+
+    #[arg(long, default_value_t = 100_000)]
+    max_iterations: usize,
+
+    /// Comma separated dichotomy variants, every variant is refined separately
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = [Dichotomy::Plain, Dichotomy::Smart])]
+    dichotomy: Vec<Dichotomy>,
+
+    /// Minimal relative radius decrease accepted by the dichotomy
+    #[arg(long, default_value_t = 0.0)]
+    eps: FloatType,
+
+    /// Make refined packings strictly feasible, keeping at least the given gap between circles
+    #[arg(long)]
+    repair: Option<FloatType>,
+
+    /// Keep the centre of mass of circles within the given distance from the container centre
+    #[arg(long)]
+    balance: Option<FloatType>,
+
+    /// Comma separated masses of circles in the original order, areas by default
+    #[arg(long, value_delimiter = ',', requires = "balance")]
+    masses: Option<Vec<FloatType>>,
+
+    /// Check every pair of circles for overlap instead of nearby ones only (same results, slower
+    /// for large instances)
+    #[arg(long)]
+    no_neighbour_list: bool,
+
+    /// Check pairs of circles on the current thread only, even for thousands of circles
+    #[arg(long)]
+    no_parallel: bool,
+
+    /// Keep only the given number of last space dilation vectors instead of the full matrix,
+    /// for thousands of circles
+    #[arg(long)]
+    memory: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Label {
+    Index,
+    Radius,
+}
+
+impl From<Label> for CircleLabel {
+    fn from(label: Label) -> Self {
+        match label {
+            Label::Index => CircleLabel::Index,
+            Label::Radius => CircleLabel::Radius,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Fill {
+    None,
+    Radius,
+    Index,
+}
+
+impl From<Fill> for CircleFill {
+    fn from(fill: Fill) -> Self {
+        match fill {
+            Fill::None => CircleFill::None,
+            Fill::Radius => CircleFill::ByRadius,
+            Fill::Index => CircleFill::ByIndex,
+        }
+    }
+}
+
+#[derive(Args)]
+struct OutputArgs {
+    /// Directory for the plotted packings
+    #[arg(long, default_value = "images")]
+    images_dir: PathBuf,
+
+    /// Also plot packings as svg
+    #[arg(long)]
+    svg: bool,
+
+    #[arg(long, default_value_t = 1000)]
+    image_size: u32,
+
+    #[arg(long, default_value = "bebas neue")]
+    font: String,
+
+    #[arg(long, value_enum, default_value_t = Fill::None)]
+    fill: Fill,
+
+    /// Label inner circles
+    #[arg(long, value_enum)]
+    labels: Option<Label>,
+
+    /// Connect centers of circles which gap is not bigger than the given tolerance
+    #[arg(long)]
+    contacts: Option<FloatType>,
+
+    /// Do not draw overlapping and sticking out circles in red
+    #[arg(long)]
+    no_highlight: bool,
 
-    let x: DVector<FloatType> = best.into_coords_vec();
-    let inner_circles_radiuses = best.inner_circles_radiuses();
-    let inner_circles_radiuses_clone = inner_circles_radiuses.clone();
+    /// Tolerance used to validate found packings and highlight violations in plots
+    #[arg(long, default_value_t = 0.0)]
+    tolerance: FloatType,
 
-    let calcfg = move |x: &DVector<FloatType>| -> (FloatType, DVector<FloatType>) {
-        calcfg(&x, &inner_circles_radiuses_clone)
+    /// Directory for the found packings, saved as json
+    #[arg(long)]
+    solutions_dir: Option<PathBuf>,
+}
+
+impl OutputArgs {
+    fn plot_options(&self) -> PlotOptions {
+        PlotOptions::new()
+            .image_size(self.image_size)
+            .font(&self.font)
+            .fill(self.fill.into())
+            .label(self.labels.map(CircleLabel::from))
+            .contact_tolerance(self.contacts)
+            .highlight_violations(!self.no_highlight)
+            .violation_tolerance(self.tolerance)
+    }
+}
+
+#[derive(Args)]
+struct HeuristicCommandArgs {
+    #[command(flatten)]
+    radiuses: RadiusesArgs,
+
+    #[command(flatten)]
+    heuristic: HeuristicArgs,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Args)]
+struct RefineArgs {
+    /// Packing saved as json or csv
+    solution: PathBuf,
+
+    #[command(flatten)]
+    ralgo: RAlgorithmArgs,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Goal {
+    /// Maximise the number of placed circles
+    Count,
+    /// Maximise the total value of placed circles
+    Value,
+}
+
+#[derive(Args)]
+struct KnapsackArgs {
+    #[command(flatten)]
+    radiuses: RadiusesArgs,
+
+    #[command(flatten)]
+    container: ContainerArgs,
+
+    /// Fixed radius, side, height or length of the container
+    #[arg(long)]
+    size: FloatType,
+
+    /// Comma separated values of the candidate circles, every circle is worth 1 by default
+    #[arg(long, value_delimiter = ',')]
+    values: Option<Vec<FloatType>>,
+
+    #[arg(long, value_enum, default_value_t = Goal::Count)]
+    goal: Goal,
+
+    #[arg(long, default_value_t = 10_000)]
+    iterations: usize,
+
+    /// Circles not bigger than this part of the biggest radius are packed as small ones
+    #[arg(long, default_value_t = 0.3)]
+    small_circle_ratio: FloatType,
+
+    #[arg(long, default_value_t = 1e-6)]
+    delta: FloatType,
+
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Args)]
+struct SolveArgs {
+    #[command(flatten)]
+    radiuses: RadiusesArgs,
+
+    #[command(flatten)]
+    heuristic: HeuristicArgs,
+
+    #[command(flatten)]
+    ralgo: RAlgorithmArgs,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let result = match cli.command.unwrap_or(Command::Solve(cli.solve)) {
+        Command::Heuristic(args) => run_heuristic_command(args),
+        Command::Refine(args) => run_refine_command(args),
+        Command::Knapsack(args) => run_knapsack_command(args),
+        Command::Solve(args) => run_solve_command(args),
     };
 
-    let ralgo_base = RAlgorithmBuilder::new()
-        .alpha(1.5)
-        .max_iterations(100_000)
-        .calcfg(Arc::new(calcfg));
+    if let Err(err) = result {
+        eprintln!("error: {err}");
+        std::process::exit(1);
+    }
+}
 
-    let (total_time_ralgo_1, improved_1) = measure_time(|| {
-        let ralgo = ralgo_base.clone().q1(0.95).build();
+fn run_heuristic_command(args: HeuristicCommandArgs) -> io::Result<()> {
+    let instance = read_instance(&args.radiuses)?;
+    run_heuristic(&instance, &args.heuristic, &args.output)?;
 
-        let x = dichotomy_step_ralgo(x.clone(), false, 0.0, ralgo);
-        let improved_best_1 =
-            CirclesPacking::from_coords_vec_and_radiuses(x, &inner_circles_radiuses);
+    Ok(())
+}
 
-        improved_best_1
-    });
+fn run_refine_command(args: RefineArgs) -> io::Result<()> {
+    let packing = CirclesPacking::load(&args.solution)
+        .map_err(|err| invalid_data(format!("{}: {err}", args.solution.display())))?;
+    run_refinement(&packing, &args.ralgo, &args.output)
+}
 
-    let (total_time_ralgo_2, improved_2) = measure_time(|| {
-        let ralgo = ralgo_base.clone().q1(1.0).build();
+fn run_knapsack_command(args: KnapsackArgs) -> io::Result<()> {
+    let instance = read_instance(&args.radiuses)?;
+    if !instance.fixed.is_empty() || !instance.obstacles.is_empty() {
+        return Err(invalid_data(
+            "knapsack does not support fixed circles and obstacles",
+        ));
+    }
+
+    let radiuses = instance.radiuses;
+    let values = args.values.unwrap_or_default();
+    if !values.is_empty() && values.len() != radiuses.len() {
+        return Err(invalid_data(format!(
+            "got {} values for {} circles",
+            values.len(),
+            radiuses.len()
+        )));
+    }
+
+    let max_small_circle_radius = radiuses
+        .iter()
+        .cloned()
+        .fold(FloatType::NEG_INFINITY, FloatType::max)
+        * args.small_circle_ratio;
+
+    let mut knapsack = KnapsackAlgorithmBuilder::new()
+        .iterations(args.iterations)
+        .rng(StdRng::seed_from_u64(args.seed))
+        .radiuses(radiuses.clone())
+        .values(values)
+        .goal(match args.goal {
+            Goal::Count => KnapsackGoal::Count,
+            Goal::Value => KnapsackGoal::Value,
+        })
+        .container(Container::new(args.container.shape(), args.size))
+        .max_small_circle_radius(max_small_circle_radius)
+        .delta(args.delta)
+        .build()?;
+
+    let (total_time, solution) =
+        measure_time(|| knapsack.find_with_observer(&mut PrintObserver::default()));
+
+    println!("knapsack");
+    println!("time = {total_time}");
+    println!(
+        "placed = {} / {}",
+        solution.packing.inner_circles.len(),
+        radiuses.len()
+    );
+    println!("value = {}", solution.value);
+    println!("{}", solution.packing.validate(args.output.tolerance));
+    save_outputs(&solution.packing, "knapsack", &args.output)
+}
 
-        let x = dichotomy_step_ralgo(x.clone(), false, 0.0, ralgo);
-        let improved_best_2 =
-            CirclesPacking::from_coords_vec_and_radiuses(x, &inner_circles_radiuses);
+fn run_solve_command(args: SolveArgs) -> io::Result<()> {
+    let instance = read_instance(&args.radiuses)?;
+    let best = run_heuristic(&instance, &args.heuristic, &args.output)?;
 
-        improved_best_2
-    });
+    run_refinement(&best, &args.ralgo, &args.output)
+}
 
-    println!("heuristic + ralgo(q1=0.95)");
-    println!("time = {total_time_ralgo_1}");
-    println!("R = {}", improved_1.main_circle.radius);
-    improved_1.plot("images/heuristic + ralgo(q1=0.95).png");
+fn read_instance(args: &RadiusesArgs) -> io::Result<Instance> {
+    if let Some(radiuses) = &args.radiuses {
+        return Ok(Instance::new(radiuses.clone()));
+    }
 
-    println!();
+    if let Some(path) = &args.radiuses_file {
+        let instance = Instance::load(path)
+            .map_err(|err| invalid_data(format!("{}: {err}", path.display())))?;
 
-    println!("heuristic + ralgo(q1=1.0)");
-    println!("time = {total_time_ralgo_2}");
-    println!("R = {}", improved_2.main_circle.radius);
-    improved_2.plot("images/heuristic + ralgo(q1=1).png");
+        if let Some(name) = &instance.name {
+            println!("instance = {name}");
+        }
+        if let Some(known_best_radius) = instance.known_best_radius {
+            println!("known best R = {known_best_radius}");
+        }
+
+        return Ok(instance);
+    }
+
+    Ok(Instance::new(
+        (1..=args.test_number).map(|x| x as FloatType).collect(),
+    ))
+}
+
+fn run_heuristic(
+    instance: &Instance,
+    args: &HeuristicArgs,
+    output: &OutputArgs,
+) -> io::Result<CirclesPacking> {
+    let radiuses = &instance.radiuses;
+    let fixed_circles: Vec<Circle> = instance.fixed.iter().map(|&c| c.into()).collect();
+    let obstacles: Vec<Circle> = instance.obstacles.iter().map(|&c| c.into()).collect();
+
+    let total_iterations = args.iterations;
+    let workers = args.workers.unwrap_or(args.threads);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.threads)
+        .build()
+        .map_err(io::Error::other)?;
+
+    let max_small_circle_radius = radiuses
+        .iter()
+        .cloned()
+        .fold(FloatType::NEG_INFINITY, FloatType::max)
+        * args.small_circle_ratio;
+
+    let container = args.container.shape();
+
+    let time_limit = args
+        .time_limit
+        .map(Duration::try_from_secs_f64)
+        .transpose()
+        .map_err(|err| invalid_data(format!("invalid time limit: {err}")))?;
+
+    let multi_start = MultiStart::new()
+        .heuristic(
+            HeuristicAlgorithmBuilder::new()
+                .radiuses(radiuses.to_vec())
+                .max_small_circle_radius(max_small_circle_radius)
+                .delta(args.delta)
+                .container(container)
+                .max_unplaced(args.max_unplaced)
+                .fixed_circles(fixed_circles)
+                .obstacles(obstacles)
+                .clearance(Clearance::new(args.clearance, args.wall_clearance))
+                .deadline(time_limit.map(|time_limit| Instant::now() + time_limit))
+                .max_stall_iterations(args.max_stall)
+                .target(args.target),
+        )
+        .workers(workers)
+        .iterations(Some(total_iterations))
+        .seed(args.seed);
+
+    let (total_time, result) =
+        measure_time(|| multi_start.run_with_observer(&pool, |_| PrintObserver::default()));
+
+    let mut result = result?;
+    let best = result
+        .best
+        .ok_or_else(|| invalid_data("heuristic found no valid packing"))?;
+    let HeuristicResult {
+        packing: best,
+        stop_reason,
+        ..
+    } = result.results.swap_remove(best);
+    let best = best.ok_or_else(|| invalid_data("heuristic found no valid packing"))?;
+
+    println!("iters = {total_iterations}");
+
+    println!("heuristic");
+    println!("time = {total_time}");
+    println!("stop reason = {stop_reason:?}");
+    println!("{} = {}", size_name(&best.container), best.container.size);
+    println!("{}", best.validate(output.tolerance));
+    save_outputs(&best, "heuristic", output)?;
 
     println!();
 
-    let (total_time_ralgo_1, improved_1) = measure_time(|| {
-        let ralgo = ralgo_base.clone().q1(0.95).build();
+    Ok(best)
+}
 
-        let x = smart_dichotomy_step_ralgo(x.clone(), 0.0, ralgo);
-        let improved_best_1 =
-            CirclesPacking::from_coords_vec_and_radiuses(x, &inner_circles_radiuses);
+fn run_refinement(
+    packing: &CirclesPacking,
+    args: &RAlgorithmArgs,
+    output: &OutputArgs,
+) -> io::Result<()> {
+    let radiuses = packing.inner_circles_radiuses();
+    let balance = match args.balance {
+        Some(max_offset) => Some(balance_masses(
+            Balance::new(max_offset),
+            args.masses.as_deref(),
+            packing,
+        )?),
+        None => packing.balance.clone(),
+    };
 
-        improved_best_1
-    });
+    // Neighbour list is enabled by the objective for large instances.
+    let mut objective = CirclesPackingObjective::new(radiuses)
+        .with_shape(packing.container.shape)
+        .with_fixed(packing.fixed.clone())?
+        .with_obstacles(packing.obstacles.clone())
+        .with_clearance(packing.clearance)
+        .with_balance(balance.clone())?;
+    if args.no_neighbour_list {
+        objective = objective.with_neighbour_list(false);
+    }
+    if args.no_parallel {
+        objective = objective.with_parallel(false);
+    }
+
+    refine_with(packing, objective, balance, args, output)
+}
 
-    let (total_time_ralgo_2, improved_2) = measure_time(|| {
-        let ralgo = ralgo_base.clone().q1(1.0).build();
+/// Balance with masses of the given packing circles, `masses` are in the original order.
+fn balance_masses(
+    balance: Balance,
+    masses: Option<&[FloatType]>,
+    packing: &CirclesPacking,
+) -> io::Result<Balance> {
+    let Some(masses) = masses else {
+        return Ok(balance);
+    };
 
-        let x = smart_dichotomy_step_ralgo(x.clone(), 0.0, ralgo);
-        let improved_best_2 =
-            CirclesPacking::from_coords_vec_and_radiuses(x, &inner_circles_radiuses);
+    let masses = packing
+        .original_indexes
+        .iter()
+        .map(|&index| {
+            masses
+                .get(index)
+                .copied()
+                .ok_or_else(|| invalid_data(format!("no mass for circle {index}")))
+        })
+        .collect::<io::Result<_>>()?;
+
+    Ok(balance.with_masses(masses))
+}
 
-        improved_best_2
-    });
+fn refine_with(
+    packing: &CirclesPacking,
+    objective: CirclesPackingObjective,
+    balance: Option<Balance>,
+    args: &RAlgorithmArgs,
+    output: &OutputArgs,
+) -> io::Result<()> {
+    // Unbalanced packings are recentred first, the dichotomy needs a feasible start.
+    let x: DVector<FloatType> = match &balance {
+        Some(balance) if balance.imbalance(&packing.inner_circles) > balance.max_offset => {
+            recentre(packing, balance).into_coords_vec()?
+        }
+        _ => packing.into_coords_vec()?,
+    };
+    let inner_circles_radiuses = packing.inner_circles_radiuses();
 
-    println!("(s) heuristic + ralgo(q1=0.95)");
-    println!("time = {total_time_ralgo_1}");
-    println!("R = {}", improved_1.main_circle.radius);
-    improved_1.plot("images/heuristic + ralgo(q1=0.95) (s).png");
+    let ralgo_base = RAlgorithmBuilder::new()
+        .alpha(args.alpha)
+        .epsx(args.epsx)
+        .epsg(args.epsg)
+        .max_iterations(args.max_iterations)
+        .dilation(match args.memory {
+            Some(window) => Dilation::Limited { window },
+            None => Dilation::Dense,
+        })
+        .objective(objective);
+
+    for &dichotomy in &args.dichotomy {
+        for &q1 in &args.q1 {
+            let ralgo = ralgo_base.clone().q1(q1).try_build()?;
+            let (total_time, improved) = measure_time(|| -> io::Result<_> {
+                let observer = &mut PrintObserver::default();
+                let result = match dichotomy {
+                    Dichotomy::Plain => dichotomy_step_ralgo_with_observer(
+                        x.clone(),
+                        false,
+                        args.eps,
+                        ralgo,
+                        observer,
+                    ),
+                    Dichotomy::Reset => dichotomy_step_ralgo_with_observer(
+                        x.clone(),
+                        true,
+                        args.eps,
+                        ralgo,
+                        observer,
+                    ),
+                    Dichotomy::Smart => smart_dichotomy_step_ralgo_with_observer(
+                        x.clone(),
+                        args.eps,
+                        ralgo,
+                        observer,
+                    ),
+                }?;
+                if result.termination == DichotomyTermination::NotANumber {
+                    println!("ralgo ran into NaN, keeping the last accepted packing");
+                }
+
+                Ok(CirclesPacking::from_coords_vec(
+                    packing.container.shape,
+                    result.x,
+                    &inner_circles_radiuses,
+                )?
+                .with_original_indexes(packing.original_indexes.clone())
+                .with_fixed(packing.fixed.clone())
+                .with_obstacles(packing.obstacles.clone())
+                .with_clearance(packing.clearance)
+                .with_balance(balance.clone()))
+            });
+            let mut improved = improved?;
+
+            let name = format!("heuristic + ralgo(q1={q1}){}", dichotomy.suffix());
+
+            println!("{name}");
+            println!("time = {total_time}");
+            println!(
+                "{} = {}",
+                size_name(&improved.container),
+                improved.container.size
+            );
+            println!("{}", improved.validate(output.tolerance));
+
+            if let Some(margin) = args.repair {
+                let repaired = repair(&improved, margin)?;
+                improved = repaired.packing;
+
+                let size_name = size_name(&improved.container);
+                println!("repaired {size_name} = {}", improved.container.size);
+                println!("{size_name} increase = {:e}", repaired.size_increase);
+                if !repaired.feasible {
+                    println!("repair did not remove every violation");
+                }
+                println!("{}", improved.validate(output.tolerance));
+            }
+            save_outputs(&improved, &name, output)?;
+
+            println!();
+        }
+    }
+
+    Ok(())
+}
 
-    println!();
+fn size_name(container: &Container) -> &'static str {
+    match container.shape {
+        ContainerShape::Circle => "R",
+        ContainerShape::Square => "side",
+        ContainerShape::Rectangle { .. } => "height",
+        ContainerShape::Strip { .. } => "length",
+    }
+}
+
+fn save_outputs(packing: &CirclesPacking, name: &str, output: &OutputArgs) -> io::Result<()> {
+    fs::create_dir_all(&output.images_dir)?;
+    let plot_options = output.plot_options();
+    packing.plot_with_options(&output_path(&output.images_dir, name, "png"), &plot_options)?;
+    if output.svg {
+        packing
+            .plot_svg_with_options(&output_path(&output.images_dir, name, "svg"), &plot_options)?;
+    }
+
+    if let Some(solutions_dir) = &output.solutions_dir {
+        fs::create_dir_all(solutions_dir)?;
+        packing
+            .save(Path::new(&output_path(solutions_dir, name, "json")))
+            .map_err(io::Error::other)?;
+    }
+
+    Ok(())
+}
+
+fn output_path(dir: &Path, name: &str, extension: &str) -> String {
+    dir.join(format!("{name}.{extension}"))
+        .to_string_lossy()
+        .into_owned()
+}
 
-    println!("(s) heuristic + ralgo(q1=1.0)");
-    println!("time = {total_time_ralgo_2}");
-    println!("R = {}", improved_2.main_circle.radius);
-    improved_2.plot("images/heuristic + ralgo(q1=1) (s).png");
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
 }
@@ -0,0 +1,238 @@
+use std::sync::{atomic::AtomicBool, Arc};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::{
+    iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator},
+    ThreadPool,
+};
+
+use crate::{
+    circles_packing::CirclesPacking,
+    error::Error,
+    heuristic_algo::{HeuristicAlgorithmBuilder, HeuristicResult},
+    observer::{NoopObserver, Observer},
+    utils::{Float, FloatType},
+};
+
+/// Independent runs of the heuristic algorithm started from different random orders.
+///
+/// Worker `i` always gets the `i`-th random stream derived from the master seed, so results
+/// depend only on the seed and the number of workers, not on the number of threads. The only
+/// exceptions are the deadline, which cuts workers short depending on how fast they run, and
+/// reaching the target, the first worker to reach it stops all the others.
+#[derive(Clone)]
+pub struct MultiStart<T: Float = FloatType> {
+    heuristic: HeuristicAlgorithmBuilder<T>,
+    workers: usize,
+    iterations: Option<usize>,
+    seed: u64,
+}
+
+impl<T: Float> Default for MultiStart<T> {
+    fn default() -> Self {
+        Self {
+            heuristic: HeuristicAlgorithmBuilder::default(),
+            workers: 1,
+            iterations: None,
+            seed: 0,
+        }
+    }
+}
+
+pub struct MultiStartResult<T: Float = FloatType> {
+    /// Result of every worker, in the order of workers.
+    pub results: Vec<HeuristicResult<T>>,
+    /// Worker with the smallest valid packing, the first one on ties.
+    pub best: Option<usize>,
+}
+
+impl<T: Float> MultiStartResult<T> {
+    pub fn best_packing(&self) -> Option<&CirclesPacking<T>> {
+        self.best
+            .and_then(|index| self.results[index].packing.as_ref())
+    }
+
+    pub fn into_best_packing(mut self) -> Option<CirclesPacking<T>> {
+        self.best
+            .and_then(|index| self.results.swap_remove(index).packing)
+    }
+}
+
+impl<T: Float> MultiStart<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Settings of every run, the rng and the stop flag are replaced. Iterations are per worker unless
+    /// [`iterations`](Self::iterations) is set.
+    pub fn heuristic(mut self, heuristic: HeuristicAlgorithmBuilder<T>) -> Self {
+        self.heuristic = heuristic;
+        self
+    }
+
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Total iterations shared between workers, the first `iterations % workers` workers do one
+    /// more. Every worker needs at least one iteration.
+    pub fn iterations(mut self, iterations: Option<usize>) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn run(&self, pool: &ThreadPool) -> Result<MultiStartResult<T>, Error> {
+        self.run_with_observer(pool, |_| NoopObserver)
+    }
+
+    /// `observer` creates the observer of every worker from its index.
+    pub fn run_with_observer<O, F>(
+        &self,
+        pool: &ThreadPool,
+        observer: F,
+    ) -> Result<MultiStartResult<T>, Error>
+    where
+        O: Observer,
+        F: Fn(usize) -> O + Sync,
+    {
+        if self.workers == 0 {
+            return Err(Error::InvalidParameter {
+                name: "workers",
+                message: "must be positive".to_string(),
+            });
+        }
+
+        if let Some(iterations) = self
+            .iterations
+            .filter(|&iterations| iterations < self.workers)
+        {
+            return Err(Error::InvalidParameter {
+                name: "iterations",
+                message: format!(
+                    "{iterations} iterations can not be shared between {} workers, every worker needs at least one",
+                    self.workers
+                ),
+            });
+        }
+
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let mut master = StdRng::seed_from_u64(self.seed);
+        let mut algorithms = Vec::with_capacity(self.workers);
+        for worker in 0..self.workers {
+            let rng = StdRng::from_seed(master.gen());
+            let mut heuristic = self
+                .heuristic
+                .clone()
+                .rng(rng)
+                .stop_flag(Some(stop_flag.clone()));
+            if let Some(iterations) = self.iterations {
+                heuristic = heuristic.iterations(
+                    iterations / self.workers + usize::from(worker < iterations % self.workers),
+                );
+            }
+            algorithms.push(heuristic.build()?);
+        }
+
+        let results: Vec<HeuristicResult<T>> = pool.install(|| {
+            algorithms
+                .into_par_iter()
+                .enumerate()
+                .map(|(worker, mut algorithm)| {
+                    algorithm.find_with_observer(None, &mut observer(worker))
+                })
+                .collect()
+        });
+
+        let best = results
+            .iter()
+            .enumerate()
+            .filter_map(|(index, result)| Some((index, result.packing.as_ref()?)))
+            .filter(|(_, packing)| packing.is_valid_pack())
+            .fold(None, |best: Option<(usize, T)>, (index, packing)| {
+                let size = packing.container.size.clone();
+                match best {
+                    Some((_, ref best_size)) if *best_size <= size => best,
+                    _ => Some((index, size)),
+                }
+            })
+            .map(|(index, _)| index);
+
+        Ok(MultiStartResult { results, best })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn multi_start(workers: usize, iterations: usize) -> MultiStart {
+        MultiStart::new()
+            .heuristic(
+                HeuristicAlgorithmBuilder::new()
+                    .radiuses((1..=8).map(|radius| radius as FloatType).collect())
+                    .max_small_circle_radius(2.0),
+            )
+            .workers(workers)
+            .iterations(Some(iterations))
+    }
+
+    #[test]
+    fn iterations_remainder_is_shared() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+
+        let result = multi_start(3, 11).run(&pool).unwrap();
+        let iterations: Vec<usize> = result
+            .results
+            .iter()
+            .map(|result| result.iterations)
+            .collect();
+
+        assert_eq!(iterations, vec![4, 4, 3]);
+    }
+
+    #[test]
+    fn results_do_not_depend_on_threads() {
+        let run_on = |threads: usize| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+
+            multi_start(4, 40)
+                .seed(7)
+                .run(&pool)
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|result| result.packing.unwrap().into_coords_vec().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(run_on(1), run_on(4));
+    }
+
+    #[test]
+    fn fewer_iterations_than_workers_are_rejected() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+
+        assert!(matches!(
+            multi_start(4, 3).run(&pool),
+            Err(Error::InvalidParameter {
+                name: "iterations",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use nalgebra::DVector;
+
+use crate::utils::{cast, to_float_type, Float, FloatType};
+
+/// Circles which may overlap each other, found with a uniform grid.
+///
+/// The list keeps every pair closer than `skin` to touching at the moment it was built, so it
+/// still has every overlapping pair while no circle moved by more than a quarter of `skin`.
+#[derive(Clone, Debug)]
+pub struct NeighbourList<T: Float = FloatType> {
+    /// Centers of circles the list was built for.
+    centers: Vec<(T, T)>,
+    skin: T,
+    /// Sorted circles `j > i` near every circle `i`.
+    neighbours: Vec<Vec<usize>>,
+}
+
+impl<T: Float> NeighbourList<T> {
+    /// List for `(x_1, ..., x_n, y_1, ..., y_n, ...)` variables, circles are near each other when
+    /// the gap between them is less than `gap + skin`.
+    pub fn new(x: &DVector<T>, radiuses: &[T], gap: T, skin: T) -> Self {
+        let n = radiuses.len();
+        let centers: Vec<(T, T)> = (0..n).map(|i| (x[i].clone(), x[n + i].clone())).collect();
+
+        let max_radius = radiuses.iter().cloned().fold(T::zero(), T::max);
+        let cell_size = cast::<T>(2.0) * max_radius + gap.clone() + skin.clone();
+        let cell = |x: &T, y: &T| {
+            (
+                to_float_type((x.clone() / cell_size.clone()).floor()) as i64,
+                to_float_type((y.clone() / cell_size.clone()).floor()) as i64,
+            )
+        };
+
+        let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (i, (x, y)) in centers.iter().enumerate() {
+            grid.entry(cell(x, y)).or_default().push(i);
+        }
+
+        let neighbours = centers
+            .iter()
+            .enumerate()
+            .map(|(i, (xi, yi))| {
+                let (cx, cy) = cell(xi, yi);
+                let mut near: Vec<usize> = Vec::new();
+
+                for dx in -1..=1 {
+                    for dy in -1..=1 {
+                        let key = (cx.saturating_add(dx), cy.saturating_add(dy));
+                        let Some(cell_circles) = grid.get(&key) else {
+                            continue;
+                        };
+
+                        near.extend(cell_circles.iter().copied().filter(|&j| {
+                            let (xj, yj) = &centers[j];
+                            let distance = radiuses[i].clone()
+                                + radiuses[j].clone()
+                                + gap.clone()
+                                + skin.clone();
+
+                            j > i
+                                && (xi.clone() - xj.clone()).powi(2)
+                                    + (yi.clone() - yj.clone()).powi(2)
+                                    < distance.clone() * distance
+                        }));
+                    }
+                }
+
+                near.sort_unstable();
+                near
+            })
+            .collect();
+
+        Self {
+            centers,
+            skin,
+            neighbours,
+        }
+    }
+
+    /// Sorted circles `j > i` which may overlap circle `i`.
+    pub fn neighbours(&self, i: usize) -> &[usize] {
+        &self.neighbours[i]
+    }
+
+    /// Whether the list still has every overlapping pair of circles with the given centers.
+    pub fn is_valid(&self, x: &DVector<T>) -> bool {
+        let n = self.centers.len();
+        if x.len() < 2 * n {
+            return false;
+        }
+
+        let max_shift = self.skin.clone() / cast(4.0);
+        self.centers.iter().enumerate().all(|(i, (xi, yi))| {
+            (x[i].clone() - xi.clone()).hypot(x[n + i].clone() - yi.clone()) <= max_shift
+        })
+    }
+}
+
+/// Neighbour list of an objective, rebuilt lazily when circles move too far.
+#[derive(Debug, Default)]
+pub(crate) struct NeighbourCache<T: Float = FloatType>(Mutex<Option<NeighbourList<T>>>);
+
+impl<T: Float> NeighbourCache<T> {
+    /// List valid for the given centers, the cached one is rebuilt if needed.
+    pub(crate) fn get(
+        &self,
+        x: &DVector<T>,
+        radiuses: &[T],
+        gap: T,
+    ) -> MutexGuard<'_, Option<NeighbourList<T>>> {
+        let mut list = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if !list.as_ref().is_some_and(|list| list.is_valid(x)) {
+            let skin = radiuses
+                .iter()
+                .fold(T::zero(), |sum, radius| sum + radius.clone())
+                / cast(radiuses.len() as FloatType);
+            *list = Some(NeighbourList::new(x, radiuses, gap, skin));
+        }
+
+        list
+    }
+}
+
+impl<T: Float> Clone for NeighbourCache<T> {
+    /// Clones start with an empty cache.
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        calcfg::{calcfg_with_clearance, CirclesPackingObjective},
+        clearance::Clearance,
+        ralgo::Objective,
+    };
+
+    /// Crowded random packing with many overlapping pairs.
+    fn crowded(n: usize, rng: &mut StdRng) -> (DVector<FloatType>, Vec<FloatType>) {
+        let radiuses: Vec<FloatType> = (0..n).map(|_| rng.gen_range(1.0..3.0)).collect();
+        let half_side = 2.0 * (n as FloatType).sqrt();
+        let x = DVector::from_iterator(
+            2 * n + 1,
+            (0..2 * n)
+                .map(|_| rng.gen_range(-half_side..half_side))
+                .chain([2.0 * half_side]),
+        );
+
+        (x, radiuses)
+    }
+
+    /// Every center moved by up to `shift` in a random direction.
+    fn moved(x: &DVector<FloatType>, shift: FloatType, rng: &mut StdRng) -> DVector<FloatType> {
+        let n = x.len() / 2;
+        let mut moved = x.clone();
+        for i in 0..n {
+            let (angle, length) = (
+                rng.gen_range(0.0..std::f64::consts::TAU),
+                rng.gen_range(0.0..=shift),
+            );
+            moved[i] += length * angle.cos();
+            moved[n + i] += length * angle.sin();
+        }
+
+        moved
+    }
+
+    #[test]
+    fn stale_list_gives_same_penalty_as_brute_force() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let (x, radiuses) = crowded(200, &mut rng);
+        let clearance = Clearance::new(0.1, 0.0);
+        let skin = 1.0;
+        let list = NeighbourList::new(&x, &radiuses, clearance.pair, skin);
+
+        for _ in 0..10 {
+            let y = moved(&x, skin / 4.0, &mut rng);
+            assert!(list.is_valid(&y));
+
+            let brute_force = calcfg_with_clearance(&y, &radiuses, &clearance, None, false);
+            let with_list = calcfg_with_clearance(&y, &radiuses, &clearance, Some(&list), false);
+
+            assert_eq!(with_list.unwrap(), brute_force.unwrap());
+        }
+    }
+
+    #[test]
+    fn objective_with_list_matches_brute_force_after_big_moves() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let (mut x, radiuses) = crowded(150, &mut rng);
+        let with_list = CirclesPackingObjective::new(radiuses.clone())
+            .with_neighbour_list(true)
+            .with_parallel(false);
+        let brute_force = CirclesPackingObjective::new(radiuses)
+            .with_neighbour_list(false)
+            .with_parallel(false);
+
+        // Skin is the mean radius (about 2), moves over a quarter of it rebuild the list.
+        for shift in [0.1, 0.5, 2.0, 0.2, 5.0, 0.4] {
+            x = moved(&x, shift, &mut rng);
+
+            assert_eq!(with_list.calcfg(&x), brute_force.calcfg(&x));
+        }
+    }
+}
@@ -0,0 +1,449 @@
+use std::{
+    fmt,
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign},
+};
+
+use approx::{AbsDiffEq, RelativeEq, UlpsEq};
+use nalgebra::{ComplexField, Field, RealField, SimdValue};
+use num_traits::{FromPrimitive, Num, One, Signed, Zero};
+use simba::scalar::SubsetOf;
+
+/// `f64` behind a box, a float type which is not `Copy` like arbitrary-precision types.
+#[derive(Clone, Debug, Default, PartialEq, PartialOrd)]
+pub(crate) struct NonCopyFloat(Box<f64>);
+
+impl NonCopyFloat {
+    pub(crate) fn new(value: f64) -> Self {
+        Self(Box::new(value))
+    }
+
+    pub(crate) fn value(&self) -> f64 {
+        *self.0
+    }
+}
+
+macro_rules! binary_ops {
+    ($($op:ident $method:ident $assign_op:ident $assign_method:ident),*) => {$(
+        impl $op for NonCopyFloat {
+            type Output = Self;
+
+            fn $method(self, other: Self) -> Self {
+                Self::new(self.value().$method(other.value()))
+            }
+        }
+
+        impl $assign_op for NonCopyFloat {
+            fn $assign_method(&mut self, other: Self) {
+                self.0.$assign_method(other.value());
+            }
+        }
+    )*};
+}
+
+binary_ops!(
+    Add add AddAssign add_assign,
+    Sub sub SubAssign sub_assign,
+    Mul mul MulAssign mul_assign,
+    Div div DivAssign div_assign,
+    Rem rem RemAssign rem_assign
+);
+
+/// Methods of `f64` taking and returning floats.
+macro_rules! forward {
+    ($($method:ident($($arg:ident),*)),* $(,)?) => {$(
+        fn $method(self $(, $arg: Self)*) -> Self {
+            Self::new(self.value().$method($($arg.value()),*))
+        }
+    )*};
+}
+
+/// Constants of `f64`.
+macro_rules! constants {
+    ($($method:ident $constant:ident),* $(,)?) => {$(
+        fn $method() -> Self {
+            Self::new(std::f64::consts::$constant)
+        }
+    )*};
+}
+
+impl Neg for NonCopyFloat {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.value())
+    }
+}
+
+impl fmt::Display for NonCopyFloat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value().fmt(f)
+    }
+}
+
+impl Zero for NonCopyFloat {
+    fn zero() -> Self {
+        Self::new(0.0)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.value() == 0.0
+    }
+}
+
+impl One for NonCopyFloat {
+    fn one() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl Num for NonCopyFloat {
+    type FromStrRadixErr = <f64 as Num>::FromStrRadixErr;
+
+    fn from_str_radix(string: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        f64::from_str_radix(string, radix).map(Self::new)
+    }
+}
+
+impl Signed for NonCopyFloat {
+    fn abs(&self) -> Self {
+        Self::new(self.value().abs())
+    }
+
+    fn abs_sub(&self, other: &Self) -> Self {
+        Self::new(Signed::abs_sub(&self.value(), &other.value()))
+    }
+
+    fn signum(&self) -> Self {
+        Self::new(self.value().signum())
+    }
+
+    fn is_positive(&self) -> bool {
+        self.value() > 0.0
+    }
+
+    fn is_negative(&self) -> bool {
+        self.value() < 0.0
+    }
+}
+
+impl FromPrimitive for NonCopyFloat {
+    fn from_i64(value: i64) -> Option<Self> {
+        Some(Self::new(value as f64))
+    }
+
+    fn from_u64(value: u64) -> Option<Self> {
+        Some(Self::new(value as f64))
+    }
+
+    fn from_f64(value: f64) -> Option<Self> {
+        Some(Self::new(value))
+    }
+}
+
+impl AbsDiffEq for NonCopyFloat {
+    type Epsilon = Self;
+
+    fn default_epsilon() -> Self {
+        Self::new(f64::default_epsilon())
+    }
+
+    fn abs_diff_eq(&self, other: &Self, epsilon: Self) -> bool {
+        self.value().abs_diff_eq(&other.value(), epsilon.value())
+    }
+}
+
+impl RelativeEq for NonCopyFloat {
+    fn default_max_relative() -> Self {
+        Self::new(f64::default_max_relative())
+    }
+
+    fn relative_eq(&self, other: &Self, epsilon: Self, max_relative: Self) -> bool {
+        self.value()
+            .relative_eq(&other.value(), epsilon.value(), max_relative.value())
+    }
+}
+
+impl UlpsEq for NonCopyFloat {
+    fn default_max_ulps() -> u32 {
+        f64::default_max_ulps()
+    }
+
+    fn ulps_eq(&self, other: &Self, epsilon: Self, max_ulps: u32) -> bool {
+        self.value()
+            .ulps_eq(&other.value(), epsilon.value(), max_ulps)
+    }
+}
+
+impl SimdValue for NonCopyFloat {
+    type Element = Self;
+    type SimdBool = bool;
+
+    const LANES: usize = 1;
+
+    fn splat(value: Self) -> Self {
+        value
+    }
+
+    fn extract(&self, _: usize) -> Self {
+        self.clone()
+    }
+
+    unsafe fn extract_unchecked(&self, _: usize) -> Self {
+        self.clone()
+    }
+
+    fn replace(&mut self, _: usize, value: Self) {
+        *self = value;
+    }
+
+    unsafe fn replace_unchecked(&mut self, _: usize, value: Self) {
+        *self = value;
+    }
+
+    fn select(self, condition: bool, other: Self) -> Self {
+        if condition {
+            self
+        } else {
+            other
+        }
+    }
+}
+
+impl Field for NonCopyFloat {}
+
+impl SubsetOf<NonCopyFloat> for NonCopyFloat {
+    fn to_superset(&self) -> Self {
+        self.clone()
+    }
+
+    fn from_superset_unchecked(element: &Self) -> Self {
+        element.clone()
+    }
+
+    fn is_in_subset(_: &Self) -> bool {
+        true
+    }
+}
+
+impl SubsetOf<NonCopyFloat> for f64 {
+    fn to_superset(&self) -> NonCopyFloat {
+        NonCopyFloat::new(*self)
+    }
+
+    fn from_superset_unchecked(element: &NonCopyFloat) -> Self {
+        element.value()
+    }
+
+    fn is_in_subset(_: &NonCopyFloat) -> bool {
+        true
+    }
+}
+
+impl SubsetOf<NonCopyFloat> for f32 {
+    fn to_superset(&self) -> NonCopyFloat {
+        NonCopyFloat::new(*self as f64)
+    }
+
+    fn from_superset_unchecked(element: &NonCopyFloat) -> Self {
+        element.value() as f32
+    }
+
+    fn is_in_subset(_: &NonCopyFloat) -> bool {
+        true
+    }
+}
+
+impl ComplexField for NonCopyFloat {
+    type RealField = Self;
+
+    forward!(
+        floor(),
+        ceil(),
+        round(),
+        trunc(),
+        fract(),
+        mul_add(a, b),
+        abs(),
+        hypot(other),
+        recip(),
+        sin(),
+        cos(),
+        tan(),
+        asin(),
+        acos(),
+        atan(),
+        sinh(),
+        cosh(),
+        tanh(),
+        asinh(),
+        acosh(),
+        atanh(),
+        log(base),
+        log2(),
+        log10(),
+        ln(),
+        ln_1p(),
+        sqrt(),
+        exp(),
+        exp2(),
+        exp_m1(),
+        powf(n),
+        cbrt(),
+    );
+
+    fn from_real(re: Self) -> Self {
+        re
+    }
+
+    fn real(self) -> Self {
+        self
+    }
+
+    fn imaginary(self) -> Self {
+        Self::zero()
+    }
+
+    fn modulus(self) -> Self {
+        self.abs()
+    }
+
+    fn modulus_squared(self) -> Self {
+        self.clone() * self
+    }
+
+    fn argument(self) -> Self {
+        if self.value() >= 0.0 {
+            Self::zero()
+        } else {
+            Self::pi()
+        }
+    }
+
+    fn norm1(self) -> Self {
+        self.abs()
+    }
+
+    fn scale(self, factor: Self) -> Self {
+        self * factor
+    }
+
+    fn unscale(self, factor: Self) -> Self {
+        self / factor
+    }
+
+    fn conjugate(self) -> Self {
+        self
+    }
+
+    fn sin_cos(self) -> (Self, Self) {
+        let (sin, cos) = self.value().sin_cos();
+        (Self::new(sin), Self::new(cos))
+    }
+
+    fn powi(self, n: i32) -> Self {
+        Self::new(self.value().powi(n))
+    }
+
+    fn powc(self, n: Self) -> Self {
+        self.powf(n)
+    }
+
+    fn is_finite(&self) -> bool {
+        self.value().is_finite()
+    }
+
+    fn try_sqrt(self) -> Option<Self> {
+        (self.value() >= 0.0).then(|| self.sqrt())
+    }
+}
+
+impl RealField for NonCopyFloat {
+    forward!(copysign(sign), max(other), min(other), atan2(other));
+
+    fn is_sign_positive(&self) -> bool {
+        self.value().is_sign_positive()
+    }
+
+    fn is_sign_negative(&self) -> bool {
+        self.value().is_sign_negative()
+    }
+
+    fn clamp(self, min: Self, max: Self) -> Self {
+        Self::new(self.value().clamp(min.value(), max.value()))
+    }
+
+    fn min_value() -> Option<Self> {
+        Some(Self::new(f64::MIN))
+    }
+
+    fn max_value() -> Option<Self> {
+        Some(Self::new(f64::MAX))
+    }
+
+    constants!(
+        pi PI,
+        two_pi TAU,
+        frac_pi_2 FRAC_PI_2,
+        frac_pi_3 FRAC_PI_3,
+        frac_pi_4 FRAC_PI_4,
+        frac_pi_6 FRAC_PI_6,
+        frac_pi_8 FRAC_PI_8,
+        frac_1_pi FRAC_1_PI,
+        frac_2_pi FRAC_2_PI,
+        frac_2_sqrt_pi FRAC_2_SQRT_PI,
+        e E,
+        log2_e LOG2_E,
+        log10_e LOG10_E,
+        ln_2 LN_2,
+        ln_10 LN_10,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DVector;
+
+    use super::*;
+    use crate::{
+        calcfg::CirclesPackingObjective,
+        heuristic_algo::HeuristicAlgorithmBuilder,
+        ralgo::{NoObjective, RAlgorithmBuilder},
+        utils::{cast, to_float_type, Float, FloatType},
+    };
+
+    /// Heuristic packing of 6 circles `r_i = i` refined by ralgo, as `f64` values.
+    fn heuristic_and_ralgo<T: Float>(radiuses: Vec<T>) -> Vec<FloatType> {
+        let packing = HeuristicAlgorithmBuilder::<T>::new()
+            .iterations(10)
+            .radiuses(radiuses)
+            .max_small_circle_radius(cast(2.0))
+            .build()
+            .unwrap()
+            .find(None)
+            .packing
+            .unwrap();
+        let x: DVector<T> = packing.into_coords_vec().unwrap();
+
+        let result = RAlgorithmBuilder::<NoObjective, T>::new()
+            .max_iterations(200)
+            .objective(CirclesPackingObjective::new(
+                packing.inner_circles_radiuses(),
+            ))
+            .try_build()
+            .unwrap()
+            .evaluate(x, T::one())
+            .unwrap();
+
+        result.x.iter().cloned().map(to_float_type).collect()
+    }
+
+    #[test]
+    fn non_copy_float_matches_f64() {
+        let radiuses: Vec<FloatType> = (1..=6).map(|radius| radius as FloatType).collect();
+
+        let non_copy =
+            heuristic_and_ralgo(radiuses.iter().map(|&r| NonCopyFloat::new(r)).collect());
+
+        assert_eq!(non_copy, heuristic_and_ralgo(radiuses));
+    }
+}
@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use crate::utils::FloatType;
+
+/// Algorithm reporting its progress.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stage {
+    Heuristic,
+    Knapsack,
+    RAlgorithm,
+    Dichotomy { step_size: FloatType },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    pub stage: Stage,
+    pub iteration: usize,
+    /// Best container size, best total value for [`Stage::Knapsack`] or best objective value
+    /// for [`Stage::RAlgorithm`].
+    pub best_value: FloatType,
+    /// Time since the algorithm was started.
+    pub elapsed: Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Signal {
+    Continue,
+    /// Stop the algorithm and return the best found solution.
+    Stop,
+}
+
+/// Hooks called by the heuristic and knapsack algorithms, ralgo and the dichotomy step ralgo.
+pub trait Observer {
+    /// Called at the start of every iteration.
+    fn on_iteration(&mut self, _progress: &Progress) -> Signal {
+        Signal::Continue
+    }
+
+    /// Called every time the best solution improves.
+    fn on_improvement(&mut self, _progress: &Progress) -> Signal {
+        Signal::Continue
+    }
+}
+
+/// Observer ignoring every event.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopObserver;
+
+impl Observer for NoopObserver {}
+
+/// Observer printing heuristic and knapsack iterations and dichotomy steps to stdout.
+#[derive(Clone, Copy, Debug)]
+pub struct PrintObserver {
+    heuristic_period: usize,
+}
+
+impl Default for PrintObserver {
+    fn default() -> Self {
+        Self {
+            heuristic_period: 10_000,
+        }
+    }
+}
+
+impl PrintObserver {
+    pub fn new(heuristic_period: usize) -> Self {
+        Self {
+            heuristic_period: heuristic_period.max(1),
+        }
+    }
+}
+
+impl Observer for PrintObserver {
+    fn on_iteration(&mut self, progress: &Progress) -> Signal {
+        match progress.stage {
+            Stage::Heuristic | Stage::Knapsack
+                if progress.iteration.is_multiple_of(self.heuristic_period) =>
+            {
+                println!("iter = {}", progress.iteration);
+            }
+            Stage::Dichotomy { step_size } => println!("step = {step_size}"),
+            _ => {}
+        }
+
+        Signal::Continue
+    }
+}
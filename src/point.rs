@@ -1,7 +1,7 @@
-use crate::utils::FloatType;
+use crate::utils::{Float, FloatType};
 
 #[derive(Clone, Copy, Debug, Default)]
-pub struct Point {
-    pub x: FloatType,
-    pub y: FloatType,
-}
\ No newline at end of file
+pub struct Point<T: Float = FloatType> {
+    pub x: T,
+    pub y: T,
+}
@@ -1,59 +1,59 @@
-use std::sync::Arc;
+use crate::{
+    error::Error,
+    utils::{cast, to_float_type, Float, FloatType},
+};
 
-use nalgebra::DVector;
-
-use crate::utils::FloatType;
-
-use super::RAlgorithm;
-
-type CalcfgType = dyn Fn(&DVector<FloatType>) -> (FloatType, DVector<FloatType>);
+use super::{Dilation, NoObjective, Objective, RAlgorithm};
 
+/// Builder of [`RAlgorithm`], which can only be built after an objective is set.
 #[derive(Clone)]
-pub struct RAlgorithmBuilder {
-    alpha: FloatType,
-    q1: FloatType,
-    epsx: FloatType,
-    epsg: FloatType,
+pub struct RAlgorithmBuilder<O = NoObjective, T: Float = FloatType> {
+    alpha: T,
+    q1: T,
+    epsx: T,
+    epsg: T,
     max_iterations: usize,
-    calcfg: Arc<CalcfgType>,
+    dilation: Dilation,
+    objective: O,
 }
 
-impl Default for RAlgorithmBuilder {
+impl<T: Float> Default for RAlgorithmBuilder<NoObjective, T> {
     fn default() -> Self {
         Self {
-            alpha: 1.5 as FloatType,
-            q1: 1.0,
-            epsx: 1e-6,
-            epsg: 1e-7,
+            alpha: cast(1.5),
+            q1: T::one(),
+            epsx: cast(1e-6),
+            epsg: cast(1e-7),
             max_iterations: 3_000,
-            calcfg: Arc::new(|_x: &DVector<FloatType>| {
-                panic!("Default calcfg should be overriden before use!")
-            }),
+            dilation: Dilation::Dense,
+            objective: NoObjective,
         }
     }
 }
 
-impl RAlgorithmBuilder {
+impl<T: Float> RAlgorithmBuilder<NoObjective, T> {
     pub fn new() -> Self {
         Self::default()
     }
+}
 
-    pub fn alpha(mut self, alpha: FloatType) -> Self {
+impl<O, T: Float> RAlgorithmBuilder<O, T> {
+    pub fn alpha(mut self, alpha: T) -> Self {
         self.alpha = alpha;
         self
     }
 
-    pub fn q1(mut self, q1: FloatType) -> Self {
+    pub fn q1(mut self, q1: T) -> Self {
         self.q1 = q1;
         self
     }
 
-    pub fn epsx(mut self, epsx: FloatType) -> Self {
+    pub fn epsx(mut self, epsx: T) -> Self {
         self.epsx = epsx;
         self
     }
 
-    pub fn epsg(mut self, epsg: FloatType) -> Self {
+    pub fn epsg(mut self, epsg: T) -> Self {
         self.epsg = epsg;
         self
     }
@@ -63,19 +63,94 @@ impl RAlgorithmBuilder {
         self
     }
 
-    pub fn calcfg(mut self, calcfg: Arc<CalcfgType>) -> Self {
-        self.calcfg = calcfg;
+    /// Storage of the space dilation matrix, [`Dilation::Limited`] for very large packings.
+    pub fn dilation(mut self, dilation: Dilation) -> Self {
+        self.dilation = dilation;
         self
     }
 
-    pub fn build(self) -> RAlgorithm {
+    pub fn objective<P: Objective<T>>(self, objective: P) -> RAlgorithmBuilder<P, T> {
+        RAlgorithmBuilder {
+            alpha: self.alpha,
+            q1: self.q1,
+            epsx: self.epsx,
+            epsg: self.epsg,
+            max_iterations: self.max_iterations,
+            dilation: self.dilation,
+            objective,
+        }
+    }
+}
+
+impl<O: Objective<T>, T: Float> RAlgorithmBuilder<O, T> {
+    pub fn build(self) -> RAlgorithm<O, T> {
         RAlgorithm {
             alpha: self.alpha,
             q1: self.q1,
             epsx: self.epsx,
             epsg: self.epsg,
             max_iterations: self.max_iterations,
-            calcfg: self.calcfg,
+            dilation: self.dilation,
+            objective: self.objective,
+        }
+    }
+
+    /// [`build`](Self::build) checking that `alpha > 1`, `0 < q1 <= 1`, the tolerances and the
+    /// [`Dilation::Limited`] window are positive and the objective has variables.
+    pub fn try_build(self) -> Result<RAlgorithm<O, T>, Error> {
+        let invalid = |name: &'static str, condition: &str, value: T| Error::InvalidParameter {
+            name,
+            message: format!("must be {condition}, got {}", to_float_type(value)),
+        };
+
+        if !(self.alpha > T::one() && self.alpha.is_finite()) {
+            return Err(invalid("alpha", "greater than 1", self.alpha));
+        }
+        if !(self.q1 > T::zero() && self.q1 <= T::one()) {
+            return Err(invalid("q1", "in (0, 1]", self.q1));
+        }
+        if !(self.epsx > T::zero() && self.epsx.is_finite()) {
+            return Err(invalid("epsx", "positive", self.epsx));
         }
+        if !(self.epsg > T::zero() && self.epsg.is_finite()) {
+            return Err(invalid("epsg", "positive", self.epsg));
+        }
+        if matches!(self.dilation, Dilation::Limited { window: 0 }) {
+            return Err(Error::InvalidParameter {
+                name: "window",
+                message: "must be positive".to_string(),
+            });
+        }
+        if self.objective.dimension() == 0 {
+            return Err(Error::EmptyInstance);
+        }
+
+        Ok(self.build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calcfg::CirclesPackingObjective;
+
+    fn builder() -> RAlgorithmBuilder<CirclesPackingObjective> {
+        RAlgorithmBuilder::new().objective(CirclesPackingObjective::new(vec![1.0, 2.0]))
+    }
+
+    #[test]
+    fn invalid_parameters_are_rejected() {
+        let rejected =
+            |builder: RAlgorithmBuilder<CirclesPackingObjective>| match builder.try_build() {
+                Err(Error::InvalidParameter { name, .. }) => name,
+                _ => panic!("invalid parameter is accepted"),
+            };
+
+        assert_eq!(rejected(builder().alpha(1.0)), "alpha");
+        assert_eq!(rejected(builder().q1(0.0)), "q1");
+        assert_eq!(rejected(builder().q1(1.5)), "q1");
+        assert_eq!(rejected(builder().epsx(0.0)), "epsx");
+        assert_eq!(rejected(builder().epsg(-1e-7)), "epsg");
+        assert!(builder().try_build().is_ok());
     }
 }
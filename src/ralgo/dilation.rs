@@ -0,0 +1,101 @@
+use std::collections::VecDeque;
+
+use nalgebra::{DMatrix, DVector};
+
+use crate::utils::Float;
+
+/// How the space dilation matrix `B` of the r-algorithm is stored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dilation {
+    /// Full `n x n` matrix, `O(n^2)` memory and time per iteration.
+    #[default]
+    Dense,
+    /// Only the last `window` dilation vectors, `B` is the product of their dilation operators.
+    /// `O(n * window)` memory and time per iteration, for thousands of variables.
+    Limited { window: usize },
+}
+
+/// Space dilation matrix `B = (I + beta r_1 r_1^T) ... (I + beta r_k r_k^T)`.
+pub(super) struct DilationMatrix<T: Float> {
+    beta: T,
+    storage: Storage<T>,
+}
+
+enum Storage<T: Float> {
+    Dense(DMatrix<T>),
+    Limited {
+        window: usize,
+        /// Dilation vectors, the oldest first.
+        vectors: VecDeque<DVector<T>>,
+    },
+}
+
+impl<T: Float> DilationMatrix<T> {
+    pub(super) fn identity(dilation: Dilation, dimension: usize, beta: T) -> Self {
+        let storage = match dilation {
+            Dilation::Dense => Storage::Dense(DMatrix::identity(dimension, dimension)),
+            Dilation::Limited { window } => Storage::Limited {
+                window,
+                vectors: VecDeque::with_capacity(window + 1),
+            },
+        };
+
+        Self { beta, storage }
+    }
+
+    /// `B v`
+    pub(super) fn mul(&self, v: &DVector<T>) -> DVector<T> {
+        match &self.storage {
+            Storage::Dense(b_matrix) => b_matrix * v,
+            Storage::Limited { vectors, .. } => vectors
+                .iter()
+                .rev()
+                .fold(v.clone(), |v, r| dilate(v, r, self.beta.clone())),
+        }
+    }
+
+    /// `B^T v`
+    pub(super) fn tr_mul(&self, v: &DVector<T>) -> DVector<T> {
+        match &self.storage {
+            Storage::Dense(b_matrix) => b_matrix.tr_mul(v),
+            Storage::Limited { vectors, .. } => vectors
+                .iter()
+                .fold(v.clone(), |v, r| dilate(v, r, self.beta.clone())),
+        }
+    }
+
+    /// Dilate space along the difference of subgradients `dg`.
+    ///
+    /// Returns `false` and keeps the matrix if `B^T dg` is zero or not finite, so that there is
+    /// no direction to dilate along.
+    pub(super) fn update(&mut self, dg: &DVector<T>) -> bool {
+        let r = self.tr_mul(dg);
+        let r_norm = r.norm();
+        if !(r_norm > T::zero() && r_norm.is_finite()) {
+            return false;
+        }
+        let r = r / r_norm;
+
+        match &mut self.storage {
+            Storage::Dense(b_matrix) => {
+                let temp = &*b_matrix * &r;
+                b_matrix.ger(self.beta.clone(), &temp, &r, T::one());
+            }
+            Storage::Limited { window, vectors } => {
+                vectors.push_back(r);
+                if vectors.len() > *window {
+                    vectors.pop_front();
+                }
+            }
+        }
+
+        true
+    }
+}
+
+/// `(I + beta r r^T) v`
+fn dilate<T: Float>(mut v: DVector<T>, r: &DVector<T>, beta: T) -> DVector<T> {
+    let scale = beta * r.dot(&v);
+    v.axpy(scale, r, T::one());
+    v
+}
@@ -1,81 +1,468 @@
 pub mod builder;
+mod dilation;
+pub mod objective;
+pub mod result;
 
-use std::sync::Arc;
+use std::time::Instant;
 
-use nalgebra::{DMatrix, DVector};
+use nalgebra::DVector;
 
-use crate::utils::FloatType;
+use crate::{
+    error::Error,
+    observer::{NoopObserver, Observer, Progress, Signal, Stage},
+    utils::{cast, checked, to_float_type, Float, FloatType},
+};
 
 pub use self::builder::RAlgorithmBuilder;
+pub use self::dilation::Dilation;
 
-pub struct RAlgorithm {
-    alpha: FloatType,
-    q1: FloatType,
-    epsx: FloatType,
-    epsg: FloatType,
+use self::dilation::DilationMatrix;
+pub use self::objective::{NoObjective, Objective};
+pub use self::result::{RAlgorithmResult, TerminationReason};
+
+/// Line search is stopped after this many steps along one direction.
+const MAX_LINE_SEARCH_STEPS: u32 = 500;
+
+pub struct RAlgorithm<O: Objective<T>, T: Float = FloatType> {
+    alpha: T,
+    q1: T,
+    epsx: T,
+    epsg: T,
     max_iterations: usize,
-    calcfg: Arc<dyn Fn(&DVector<FloatType>) -> (FloatType, DVector<FloatType>)>,
+    dilation: Dilation,
+    objective: O,
 }
 
-impl RAlgorithm {
-    pub fn evaluate(&self, mut x: DVector<FloatType>, mut h: FloatType) -> DVector<FloatType> {
-        let mut b_matrix = DMatrix::<FloatType>::identity(x.len(), x.len());
+impl<O: Objective<T>, T: Float> RAlgorithm<O, T> {
+    pub fn objective(&self) -> &O {
+        &self.objective
+    }
+
+    /// Minimise the objective from `x` with the initial step `h`.
+    ///
+    /// Fails with [`Error::NotANumber`] as soon as the objective value or subgradient is not
+    /// finite.
+    pub fn evaluate(&self, x: DVector<T>, h: T) -> Result<RAlgorithmResult<T>, Error> {
+        self.evaluate_with_observer(x, h, &mut NoopObserver)
+    }
 
-        let mut result_x = x.clone();
-        let (mut result_f, mut g0) = (self.calcfg)(&result_x);
-        let beta_v = 1.0 / self.alpha - 1.0;
+    pub fn evaluate_with_observer(
+        &self,
+        x: DVector<T>,
+        h: T,
+        observer: &mut dyn Observer,
+    ) -> Result<RAlgorithmResult<T>, Error> {
+        self.check_variables(&x)?;
+
+        let fixed = self.objective.fixed_variables();
+        if fixed.is_empty() {
+            return self.search(x, h, observer, |x| self.objective.calcfg(x));
+        }
+
+        // Fixed variables are kept out of the search space, the search runs over free ones only.
+        let free: Vec<usize> = (0..x.len())
+            .filter(|i| fixed.binary_search(i).is_err())
+            .collect();
+        let gather =
+            |v: &DVector<T>| DVector::from_iterator(free.len(), free.iter().map(|&i| v[i].clone()));
+        let scatter = |y: &DVector<T>| {
+            let mut full = x.clone();
+            for (k, &i) in free.iter().enumerate() {
+                full[i] = y[k].clone();
+            }
+            full
+        };
+
+        let mut result = self.search(gather(&x), h, observer, |y| {
+            let (f, g) = self.objective.calcfg(&scatter(y));
+            (f, gather(&g))
+        })?;
+        result.x = scatter(&result.x);
+
+        Ok(result)
+    }
+
+    /// Check that `x` has a finite value of every variable of the objective.
+    pub(crate) fn check_variables(&self, x: &DVector<T>) -> Result<(), Error> {
+        let expected = self.objective.dimension();
+        if x.len() != expected {
+            return Err(Error::DimensionMismatch {
+                expected,
+                found: x.len(),
+            });
+        }
 
-        if g0.norm() < self.epsg {
-            return result_x;
+        if x.iter().any(|value| !value.is_finite()) {
+            return Err(Error::NotANumber);
         }
 
-        for _ in 0..self.max_iterations {
-            let mut g1: DVector<FloatType> = b_matrix.tr_mul(&g0);
+        Ok(())
+    }
+
+    fn search<F>(
+        &self,
+        mut x: DVector<T>,
+        mut h: T,
+        observer: &mut dyn Observer,
+        calcfg: F,
+    ) -> Result<RAlgorithmResult<T>, Error>
+    where
+        F: Fn(&DVector<T>) -> (T, DVector<T>),
+    {
+        let start_time = Instant::now();
+        let beta_v = T::one() / self.alpha.clone() - T::one();
+        let mut b_matrix = DilationMatrix::identity(self.dilation, x.len(), beta_v);
+
+        let mut result_x = x.clone();
+        let (mut result_f, mut g0) = checked(calcfg(&result_x))?;
+        let (mut iterations, mut evaluations) = (0, 1);
 
-            let dx = &b_matrix * (&g1 / g1.norm());
-            let dx_norm = dx.norm();
+        let termination = 'search: {
+            if g0.norm() < self.epsg {
+                break 'search TerminationReason::SmallGradient;
+            }
 
-            let mut f;
-            let (mut d, mut ls, mut ddx) = (1.0 as FloatType, 0_u32, 0.0 as FloatType);
-            while d > 0.0 {
-                x.axpy(-h, &dx, 1.0);
-                ddx += h * dx_norm;
+            for iteration in 0..self.max_iterations {
+                let progress = Progress {
+                    stage: Stage::RAlgorithm,
+                    iteration,
+                    best_value: to_float_type(result_f.clone()),
+                    elapsed: start_time.elapsed(),
+                };
+                if observer.on_iteration(&progress) == Signal::Stop {
+                    break 'search TerminationReason::Interrupted;
+                }
 
-                (f, g1) = (self.calcfg)(&x);
-                if f < result_f {
-                    (result_f, result_x) = (f, x.clone());
+                iterations += 1;
+                let mut g1: DVector<T> = b_matrix.tr_mul(&g0);
+                let g1_norm = g1.norm();
+                // Rounding of a low precision `T` can dilate the subgradient away entirely.
+                if !(g1_norm > T::zero() && g1_norm.is_finite()) {
+                    break 'search TerminationReason::DegenerateDilation;
                 }
 
-                if g1.norm() < self.epsg {
-                    return result_x;
+                let dx = b_matrix.mul(&(&g1 / g1_norm));
+                let dx_norm = dx.norm();
+
+                let mut f;
+                let (mut d, mut ls, mut ddx) = (T::one(), 0_u32, T::zero());
+                while d > T::zero() {
+                    x.axpy(-h.clone(), &dx, T::one());
+                    ddx += h.clone() * dx_norm.clone();
+
+                    (f, g1) = checked(calcfg(&x))?;
+                    evaluations += 1;
+                    if f < result_f {
+                        (result_f, result_x) = (f, x.clone());
+
+                        let progress = Progress {
+                            best_value: to_float_type(result_f.clone()),
+                            elapsed: start_time.elapsed(),
+                            ..progress
+                        };
+                        if observer.on_improvement(&progress) == Signal::Stop {
+                            break 'search TerminationReason::Interrupted;
+                        }
+                    }
+
+                    if g1.norm() < self.epsg {
+                        break 'search TerminationReason::SmallGradient;
+                    }
+
+                    ls += 1;
+                    if ls % 3 == 0 {
+                        h *= cast(1.1);
+                    }
+
+                    if ls > MAX_LINE_SEARCH_STEPS {
+                        break 'search TerminationReason::LineSearchLimit;
+                    }
+
+                    d = dx.dot(&g1);
                 }
 
-                ls += 1;
-                if ls % 3 == 0 {
-                    h *= 1.1;
+                if ls == 1 {
+                    h *= self.q1.clone();
                 }
 
-                if ls > 500 {
-                    return result_x;
+                if ddx < self.epsx {
+                    break 'search TerminationReason::SmallStep;
                 }
 
-                d = dx.dot(&g1);
+                if !b_matrix.update(&(&g1 - &g0)) {
+                    break 'search TerminationReason::DegenerateDilation;
+                }
+                g0 = g1;
             }
 
-            if ls == 1 {
-                h *= self.q1;
-            }
+            TerminationReason::MaxIterations
+        };
+
+        Ok(RAlgorithmResult {
+            x: result_x,
+            f: result_f,
+            iterations,
+            evaluations,
+            h,
+            termination,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::{
+        calcfg::CirclesPackingObjective, circle::Circle,
+        dichotomy_step_ralgo::dichotomy_step_ralgo, heuristic_algo::HeuristicAlgorithmBuilder,
+        point::Point,
+    };
+
+    /// `|x|` which turns into NaN once `x` goes below -1.
+    struct NanBelow;
 
-            if ddx < self.epsx {
-                return result_x;
+    impl Objective for NanBelow {
+        fn value(&self, x: &DVector<FloatType>) -> FloatType {
+            if x[0] < -1.0 {
+                FloatType::NAN
+            } else {
+                x[0].abs()
             }
+        }
+
+        fn subgradient(&self, x: &DVector<FloatType>) -> DVector<FloatType> {
+            DVector::from_element(1, x[0].signum())
+        }
+
+        fn dimension(&self) -> usize {
+            1
+        }
+    }
+
+    /// `|x|` counting its evaluations, with zero subgradient at 0.
+    #[derive(Default)]
+    struct Abs {
+        evaluations: Cell<usize>,
+    }
+
+    impl Objective for Abs {
+        fn value(&self, x: &DVector<FloatType>) -> FloatType {
+            self.evaluations.set(self.evaluations.get() + 1);
+            x[0].abs()
+        }
+
+        fn subgradient(&self, x: &DVector<FloatType>) -> DVector<FloatType> {
+            DVector::from_element(1, if x[0] == 0.0 { 0.0 } else { x[0].signum() })
+        }
 
-            let r = b_matrix.tr_mul(&(&g1 - &g0)).normalize();
-            let temp = &b_matrix * &r;
-            b_matrix.ger(beta_v, &temp, &r, 1.0);
-            g0 = g1;
+        fn dimension(&self) -> usize {
+            1
         }
+    }
+
+    /// `-x`, which line search follows forever.
+    struct Descending;
+
+    impl Objective for Descending {
+        fn value(&self, x: &DVector<FloatType>) -> FloatType {
+            -x[0]
+        }
+
+        fn subgradient(&self, _x: &DVector<FloatType>) -> DVector<FloatType> {
+            DVector::from_element(1, -1.0)
+        }
+
+        fn dimension(&self) -> usize {
+            1
+        }
+    }
+
+    /// Minimise `|x|` from `x` with the step `h`, checking the reported evaluations count.
+    fn minimise_abs(ralgo: RAlgorithmBuilder, x: FloatType, h: FloatType) -> RAlgorithmResult {
+        let ralgo = ralgo.objective(Abs::default()).build();
+        let result = ralgo.evaluate(DVector::from_element(1, x), h).unwrap();
+
+        assert_eq!(result.evaluations, ralgo.objective().evaluations.get());
+        result
+    }
+
+    #[test]
+    fn termination_reasons_are_reported() {
+        let result = minimise_abs(RAlgorithmBuilder::new(), 0.0, 1.0);
+        assert_eq!(result.termination, TerminationReason::SmallGradient);
+        assert_eq!((result.iterations, result.evaluations), (0, 1));
+
+        // The first step lands exactly in the minimum.
+        let result = minimise_abs(RAlgorithmBuilder::new(), 1.0, 1.0);
+        assert_eq!(result.termination, TerminationReason::SmallGradient);
+        assert_eq!((result.iterations, result.evaluations), (1, 2));
+        assert_eq!(result.f, 0.0);
+
+        let result = minimise_abs(RAlgorithmBuilder::new(), 0.3, 1.0);
+        assert_eq!(result.termination, TerminationReason::SmallStep);
+        assert!(result.f < 1e-6);
+
+        let result = minimise_abs(RAlgorithmBuilder::new().max_iterations(5), 10.0, 0.01);
+        assert_eq!(result.termination, TerminationReason::MaxIterations);
+        assert_eq!(result.iterations, 5);
+        assert!(result.f < 10.0);
+
+        let result = RAlgorithmBuilder::new()
+            .objective(Descending)
+            .build()
+            .evaluate(DVector::zeros(1), 1.0)
+            .unwrap();
+        assert_eq!(result.termination, TerminationReason::LineSearchLimit);
+        assert_eq!(result.iterations, 1);
+        assert_eq!(result.evaluations, 2 + MAX_LINE_SEARCH_STEPS as usize);
+    }
+
+    #[test]
+    fn nan_during_search_is_reported() {
+        let ralgo = RAlgorithmBuilder::new().objective(NanBelow).build();
+
+        let result = ralgo.evaluate(DVector::from_element(1, 0.5), 10.0);
+
+        assert!(matches!(result, Err(Error::NotANumber)));
+    }
+
+    #[test]
+    fn wrong_number_of_variables_is_rejected() {
+        let ralgo = RAlgorithmBuilder::new().objective(NanBelow).build();
+
+        let result = ralgo.evaluate(DVector::zeros(2), 1.0);
+
+        assert!(matches!(
+            result,
+            Err(Error::DimensionMismatch {
+                expected: 1,
+                found: 2
+            })
+        ));
+    }
+
+    /// Heuristic packing of 10 circles `r_i = i` stretched by 2%, so that there is room to
+    /// improve it, with the radiuses.
+    fn stretched_packing() -> (DVector<FloatType>, Vec<FloatType>) {
+        let packing = HeuristicAlgorithmBuilder::new()
+            .iterations(20)
+            .radiuses((1..=10).map(|radius| radius as FloatType).collect())
+            .max_small_circle_radius(2.0)
+            .build()
+            .unwrap()
+            .find(None)
+            .packing
+            .unwrap();
+
+        (
+            packing.into_coords_vec().unwrap() * 1.02,
+            packing.inner_circles_radiuses(),
+        )
+    }
+
+    fn refine(dilation: Dilation) -> (FloatType, FloatType) {
+        let (x, radiuses) = stretched_packing();
+        let start = x[x.len() - 1];
+        let result = RAlgorithmBuilder::new()
+            .max_iterations(500)
+            .dilation(dilation)
+            .objective(CirclesPackingObjective::new(radiuses))
+            .try_build()
+            .unwrap()
+            .evaluate(x, 0.1)
+            .unwrap();
+
+        (start, result.f)
+    }
+
+    #[test]
+    fn limited_dilation_with_full_window_matches_dense() {
+        let (_, dense) = refine(Dilation::Dense);
+        let (_, limited) = refine(Dilation::Limited { window: 500 });
+
+        assert!((limited - dense).abs() <= 1e-9 * dense);
+    }
+
+    #[test]
+    fn limited_dilation_with_moderate_window_improves() {
+        let (start, limited) = refine(Dilation::Limited { window: 10 });
+
+        assert!(limited < start - 0.1);
+    }
+
+    #[test]
+    fn f32_heuristic_packing_is_refined() {
+        let packing = HeuristicAlgorithmBuilder::<f32>::new()
+            .iterations(100)
+            .radiuses((1..=10).map(|radius| radius as f32).collect())
+            .max_small_circle_radius(2.0)
+            .build()
+            .unwrap()
+            .find(None)
+            .packing
+            .unwrap();
+        let x = packing.into_coords_vec().unwrap();
+        let start = x[x.len() - 1];
+        let ralgo = RAlgorithmBuilder::<NoObjective, f32>::new()
+            .max_iterations(2000)
+            .objective(CirclesPackingObjective::new(
+                packing.inner_circles_radiuses(),
+            ))
+            .try_build()
+            .unwrap();
+
+        let x = dichotomy_step_ralgo(x, false, 1e-5, ralgo).unwrap().x;
+
+        assert!(x.iter().all(|value| value.is_finite()));
+        assert!(x[x.len() - 1] <= start);
+    }
+
+    #[test]
+    fn refinement_keeps_fixed_circles_in_place() {
+        let fixed = Circle::new(2.5, Point { x: 1.0, y: -2.0 });
+        let packing = HeuristicAlgorithmBuilder::new()
+            .iterations(20)
+            .radiuses((1..=8).map(|radius| radius as FloatType).collect())
+            .max_small_circle_radius(2.0)
+            .fixed_circles(vec![fixed])
+            .build()
+            .unwrap()
+            .find(None)
+            .packing
+            .unwrap();
+        let x = packing.into_coords_vec().unwrap();
+        let n = packing.inner_circles.len();
+        let ralgo = RAlgorithmBuilder::new()
+            .objective(
+                CirclesPackingObjective::new(packing.inner_circles_radiuses())
+                    .with_fixed(packing.fixed.clone())
+                    .unwrap(),
+            )
+            .build();
+
+        let y = dichotomy_step_ralgo(x.clone(), false, 1e-3, ralgo)
+            .unwrap()
+            .x;
+
+        assert!(y[2 * n] < x[2 * n]);
+        assert_eq!((y[n - 1], y[2 * n - 1]), (1.0, -2.0));
+        for i in (0..n).filter(|&i| packing.is_fixed(i)) {
+            assert_eq!((y[i], y[n + i]), (x[i], x[n + i]));
+        }
+    }
+
+    #[test]
+    fn empty_dilation_window_is_rejected() {
+        let result = RAlgorithmBuilder::new()
+            .dilation(Dilation::Limited { window: 0 })
+            .objective(NanBelow)
+            .try_build();
 
-        result_x
+        assert!(matches!(
+            result,
+            Err(Error::InvalidParameter { name: "window", .. })
+        ));
     }
 }
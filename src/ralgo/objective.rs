@@ -0,0 +1,31 @@
+use nalgebra::DVector;
+
+use crate::utils::{Float, FloatType};
+
+/// Nonsmooth function minimised by [`RAlgorithm`](super::RAlgorithm).
+pub trait Objective<T: Float = FloatType> {
+    fn value(&self, x: &DVector<T>) -> T;
+
+    fn subgradient(&self, x: &DVector<T>) -> DVector<T>;
+
+    /// Number of variables.
+    fn dimension(&self) -> usize;
+
+    fn name(&self) -> &str {
+        "objective"
+    }
+
+    /// Value and subgradient at `x`, override when they are cheaper to evaluate together.
+    fn calcfg(&self, x: &DVector<T>) -> (T, DVector<T>) {
+        (self.value(x), self.subgradient(x))
+    }
+
+    /// Indexes of variables which must keep their initial values, sorted.
+    fn fixed_variables(&self) -> Vec<usize> {
+        Vec::new()
+    }
+}
+
+/// Placeholder of [`RAlgorithmBuilder`](super::RAlgorithmBuilder) without an objective.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoObjective;
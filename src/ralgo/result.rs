@@ -0,0 +1,34 @@
+use nalgebra::DVector;
+
+use crate::utils::{Float, FloatType};
+
+/// Why [`RAlgorithm::evaluate`](super::RAlgorithm::evaluate) stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TerminationReason {
+    /// Subgradient norm fell below `epsg`.
+    SmallGradient,
+    /// Total step of the last line search was shorter than `epsx`.
+    SmallStep,
+    /// Line search made too many steps along one direction.
+    LineSearchLimit,
+    MaxIterations,
+    /// Search direction or dilation vector vanished or overflowed, e.g. in the rounding of
+    /// `f32`.
+    DegenerateDilation,
+    /// Observer requested to stop.
+    Interrupted,
+}
+
+#[derive(Clone, Debug)]
+pub struct RAlgorithmResult<T: Float = FloatType> {
+    /// Best found point.
+    pub x: DVector<T>,
+    /// Objective value at `x`.
+    pub f: T,
+    pub iterations: usize,
+    /// Number of objective evaluations.
+    pub evaluations: usize,
+    /// Step size at the end of the search.
+    pub h: T,
+    pub termination: TerminationReason,
+}
@@ -0,0 +1,345 @@
+use crate::{
+    circle::Circle,
+    circles_packing::CirclesPacking,
+    container::{Container, ContainerShape},
+    error::Error,
+    point::Point,
+    utils::FloatType,
+};
+
+const MAX_PUSH_ITERATIONS: usize = 10_000;
+
+/// Relative excess of the scale separating circles, so that rounding does not leave them
+/// overlapping.
+const SCALE_SLACK: FloatType = 1e-12;
+
+pub struct RepairResult {
+    pub packing: CirclesPacking,
+    /// How much the container size (radius, side, height or length) grew to make the packing
+    /// feasible.
+    pub size_increase: FloatType,
+    /// Whether the repaired packing passes validation without tolerance. Scaling does not
+    /// separate pairs with fixed circles, obstacles and, in a rectangle or a strip, pairs on
+    /// the same line along the free side.
+    pub feasible: bool,
+}
+
+/// Make packing with small violations strictly feasible.
+///
+/// Overlapping circles are pushed apart and circles sticking out are pulled inside, so that
+/// every gap is at least `margin` on top of the packing clearance. Fixed circles never move,
+/// obstacles push circles away.
+/// If that does not converge, centers of movable circles are scaled away from (0, 0) (only
+/// along the free side for a rectangle or a strip) and the container grows just enough to
+/// contain every circle. Every inner circle must have a center.
+pub fn repair(packing: &CirclesPacking, margin: FloatType) -> Result<RepairResult, Error> {
+    let container = packing.container;
+    let clearance = packing.clearance;
+    // Required gaps between circles and from circles to the wall.
+    let (gap, wall_gap) = (clearance.pair + margin, clearance.wall + margin);
+    let radiuses = packing.inner_circles_radiuses();
+    let fixed: Vec<bool> = (0..radiuses.len()).map(|i| packing.is_fixed(i)).collect();
+    let mut centers: Vec<Point> = packing.centers()?;
+
+    let mut converged = false;
+    for _ in 0..MAX_PUSH_ITERATIONS {
+        let mut violated = false;
+
+        for i in 0..centers.len() {
+            for j in i + 1..centers.len() {
+                let (dx, dy) = (centers[j].x - centers[i].x, centers[j].y - centers[i].y);
+                let distance = dx.hypot(dy);
+                let required = radiuses[i] + radiuses[j] + gap;
+
+                if distance >= required || (fixed[i] && fixed[j]) {
+                    continue;
+                }
+
+                violated = true;
+                let (ux, uy) = if distance > 0.0 {
+                    (dx / distance, dy / distance)
+                } else {
+                    let angle = j as FloatType;
+                    (angle.cos(), angle.sin())
+                };
+                // Movable circle is pushed the whole way from a fixed one.
+                let (share_i, share_j) = match (fixed[i], fixed[j]) {
+                    (true, _) => (0.0, 1.0),
+                    (_, true) => (1.0, 0.0),
+                    _ => (0.5, 0.5),
+                };
+                let push = required - distance;
+
+                centers[i].x -= share_i * push * ux;
+                centers[i].y -= share_i * push * uy;
+                centers[j].x += share_j * push * ux;
+                centers[j].y += share_j * push * uy;
+            }
+        }
+
+        for (i, center) in centers.iter_mut().enumerate() {
+            if fixed[i] {
+                continue;
+            }
+
+            for obstacle in &packing.obstacles {
+                let Some(obstacle_center) = obstacle.center else {
+                    continue;
+                };
+                let (dx, dy) = (center.x - obstacle_center.x, center.y - obstacle_center.y);
+                let distance = dx.hypot(dy);
+                let required = radiuses[i] + obstacle.radius + gap;
+
+                if distance < required {
+                    violated = true;
+                    let (ux, uy) = if distance > 0.0 {
+                        (dx / distance, dy / distance)
+                    } else {
+                        (1.0, 0.0)
+                    };
+                    center.x += (required - distance) * ux;
+                    center.y += (required - distance) * uy;
+                }
+            }
+        }
+
+        for ((center, radius), &is_fixed) in centers.iter_mut().zip(&radiuses).zip(&fixed) {
+            if !is_fixed && container.excess(*center, radius + wall_gap) > 0.0 {
+                violated = true;
+                *center = container.clamp(*center, radius + wall_gap);
+            }
+        }
+
+        if !violated {
+            converged = true;
+            break;
+        }
+    }
+
+    if !converged {
+        match container.shape {
+            ContainerShape::Rectangle { .. } => {
+                scale_apart_along(&mut centers, &radiuses, &fixed, gap, Axis::Y)
+            }
+            ContainerShape::Strip { .. } => {
+                scale_apart_along(&mut centers, &radiuses, &fixed, gap, Axis::X)
+            }
+            _ => scale_apart(&mut centers, &radiuses, &fixed, gap),
+        }
+    }
+
+    let new_size = centers
+        .iter()
+        .zip(&radiuses)
+        .map(|(&center, radius)| container.required_size(center, radius + wall_gap))
+        .fold(container.size, FloatType::max);
+
+    let inner_circles = centers
+        .into_iter()
+        .zip(&radiuses)
+        .map(|(center, &radius)| Circle::new(radius, center))
+        .collect();
+
+    let repaired = CirclesPacking::new(Container::new(container.shape, new_size), inner_circles)
+        .with_original_indexes(packing.original_indexes.clone())
+        .with_fixed(fixed)
+        .with_obstacles(packing.obstacles.clone())
+        .with_clearance(clearance)
+        .with_balance(packing.balance.clone());
+    let feasible = repaired.validate(0.0).is_valid();
+
+    Ok(RepairResult {
+        packing: repaired,
+        size_increase: new_size - container.size,
+        feasible,
+    })
+}
+
+/// Scale centers of movable circles from (0, 0), so that every pair of movable circles has at
+/// least `gap` between them.
+///
+/// Scaling can not separate coincident centers, so they are pushed apart first the same way
+/// as in [`repair`].
+fn scale_apart(centers: &mut [Point], radiuses: &[FloatType], fixed: &[bool], gap: FloatType) {
+    for i in 0..centers.len() {
+        for j in i + 1..centers.len() {
+            if fixed[i] || fixed[j] || centers[i].x != centers[j].x || centers[i].y != centers[j].y
+            {
+                continue;
+            }
+
+            let angle = j as FloatType;
+            let half_push = (radiuses[i] + radiuses[j] + gap) / 2.0;
+            centers[i].x -= half_push * angle.cos();
+            centers[i].y -= half_push * angle.sin();
+            centers[j].x += half_push * angle.cos();
+            centers[j].y += half_push * angle.sin();
+        }
+    }
+
+    let mut scale: FloatType = 1.0;
+
+    for i in 0..centers.len() {
+        for j in i + 1..centers.len() {
+            if fixed[i] || fixed[j] {
+                continue;
+            }
+
+            let distance = (centers[i].x - centers[j].x).hypot(centers[i].y - centers[j].y);
+            if distance > 0.0 {
+                scale = scale.max((radiuses[i] + radiuses[j] + gap) / distance);
+            }
+        }
+    }
+
+    let scale = scale * (1.0 + SCALE_SLACK);
+    for (center, _) in centers
+        .iter_mut()
+        .zip(fixed)
+        .filter(|(_, &is_fixed)| !is_fixed)
+    {
+        center.x *= scale;
+        center.y *= scale;
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Axis {
+    X,
+    Y,
+}
+
+/// Scale one coordinate of movable centers from (0, 0), keeping the other one within the fixed
+/// width.
+///
+/// Pairs on the same line along the axis can not be separated this way and are left as is.
+fn scale_apart_along(
+    centers: &mut [Point],
+    radiuses: &[FloatType],
+    fixed: &[bool],
+    gap: FloatType,
+    axis: Axis,
+) {
+    let along = |point: &Point| match axis {
+        Axis::X => (point.x, point.y),
+        Axis::Y => (point.y, point.x),
+    };
+    let mut scale: FloatType = 1.0;
+
+    for i in 0..centers.len() {
+        for j in i + 1..centers.len() {
+            if fixed[i] || fixed[j] {
+                continue;
+            }
+
+            let ((ai, bi), (aj, bj)) = (along(&centers[i]), along(&centers[j]));
+            let (da, db) = (ai - aj, bi - bj);
+            let required = radiuses[i] + radiuses[j] + gap;
+
+            if da != 0.0 && db.abs() < required {
+                scale = scale.max((required * required - db * db).sqrt() / da.abs());
+            }
+        }
+    }
+
+    let scale = scale * (1.0 + SCALE_SLACK);
+    for (center, _) in centers
+        .iter_mut()
+        .zip(fixed)
+        .filter(|(_, &is_fixed)| !is_fixed)
+    {
+        match axis {
+            Axis::X => center.x *= scale,
+            Axis::Y => center.y *= scale,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repaired_packing_is_valid_without_margin() {
+        let circles = vec![
+            Circle::new(1.0, Point { x: -0.9, y: 0.0 }),
+            Circle::new(1.0, Point { x: 0.9, y: 0.0 }),
+        ];
+        let packing = CirclesPacking::new(Container::circle(2.0), circles);
+
+        let repaired = repair(&packing, 0.0).unwrap();
+
+        assert!(repaired.feasible);
+        assert!(repaired.packing.is_valid_pack());
+    }
+
+    #[test]
+    fn overlap_with_fixed_circle_is_reported() {
+        let circles = vec![
+            Circle::new(1.0, Point { x: -0.5, y: 0.0 }),
+            Circle::new(1.0, Point { x: 0.5, y: 0.0 }),
+        ];
+        let packing =
+            CirclesPacking::new(Container::circle(3.0), circles).with_fixed(vec![true, true]);
+
+        let repaired = repair(&packing, 0.0).unwrap();
+
+        assert!(!repaired.feasible);
+        assert!(!repaired.packing.is_valid_pack());
+    }
+
+    #[test]
+    fn coincident_centers_are_scaled_apart() {
+        let mut centers = vec![Point { x: 1.0, y: 0.0 }, Point { x: 1.0, y: 0.0 }];
+
+        scale_apart(&mut centers, &[1.0, 1.0], &[false, false], 0.5);
+
+        assert!(centers.iter().all(|c| c.x.is_finite() && c.y.is_finite()));
+        let distance = (centers[0].x - centers[1].x).hypot(centers[0].y - centers[1].y);
+        assert!(distance >= 2.5 - 1e-12);
+    }
+
+    #[test]
+    fn overfull_rectangle_grows_its_height() {
+        // Five unit circles do not fit into a 4 x 4 rectangle, so pushing them does not converge.
+        let circles = [
+            (-1.0, -1.0),
+            (1.0, -1.0),
+            (-1.0, 1.0),
+            (1.0, 1.0),
+            (0.1, 0.2),
+        ]
+        .into_iter()
+        .map(|(x, y)| Circle::new(1.0, Point { x, y }))
+        .collect();
+        let packing = CirclesPacking::new(Container::rectangle(4.0, 4.0), circles);
+
+        let repaired = repair(&packing, 0.0).unwrap();
+
+        assert!(repaired.feasible);
+        assert!(repaired.packing.is_valid_pack());
+        assert!(repaired.size_increase > 0.0);
+        assert_eq!(
+            repaired.packing.container.shape,
+            ContainerShape::Rectangle { width: 4.0 }
+        );
+    }
+
+    #[test]
+    fn circles_are_pushed_off_obstacles() {
+        let circles = vec![
+            Circle::new(1.0, Point { x: 1.5, y: 0.0 }),
+            Circle::new(1.0, Point { x: -2.0, y: 0.5 }),
+        ];
+        let obstacle = Circle::new(1.0, Point { x: 0.0, y: 0.0 });
+        let packing =
+            CirclesPacking::new(Container::circle(5.0), circles).with_obstacles(vec![obstacle]);
+        assert!(!packing.is_valid_pack());
+
+        let repaired = repair(&packing, 0.0).unwrap();
+
+        assert!(repaired.feasible);
+        assert!(repaired.packing.is_valid_pack());
+        assert_eq!(repaired.size_increase, 0.0);
+    }
+}
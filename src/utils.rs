@@ -1,7 +1,55 @@
 use std::time::Instant;
 
+use nalgebra::{DVector, RealField};
+
+use crate::error::Error;
+
 pub type FloatType = f64;
 
+/// Float types packings are generic over, e.g. `f32` for fast screening passes or an
+/// arbitrary-precision type for certifying records, which does not have to be `Copy`.
+/// [`FloatType`] is the default everywhere.
+pub trait Float: RealField + Default {}
+
+impl<T: RealField + Default> Float for T {}
+
+/// Constant of a generic float type.
+pub fn cast<T: Float>(value: FloatType) -> T {
+    nalgebra::convert(value)
+}
+
+/// Generic float as [`FloatType`], e.g. for reporting progress.
+pub fn to_float_type<T: Float>(value: T) -> FloatType {
+    value.to_subset_unchecked()
+}
+
+/// Check that there are circles and every radius is a positive finite number.
+pub fn check_radiuses<T: Float>(radiuses: &[T]) -> Result<(), Error> {
+    if radiuses.is_empty() {
+        return Err(Error::EmptyInstance);
+    }
+
+    match radiuses
+        .iter()
+        .position(|radius| !(*radius > T::zero() && radius.clone().is_finite()))
+    {
+        Some(index) => Err(Error::InvalidRadius {
+            index,
+            radius: to_float_type(radiuses[index].clone()),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Objective value and subgradient, unless any of them is not finite.
+pub(crate) fn checked<T: Float>((f, g): (T, DVector<T>)) -> Result<(T, DVector<T>), Error> {
+    if f.is_finite() && g.iter().all(|value| value.is_finite()) {
+        Ok((f, g))
+    } else {
+        Err(Error::NotANumber)
+    }
+}
+
 pub fn measure_time<F, T>(function: F) -> (FloatType, T)
 where
     F: FnOnce() -> T,
@@ -0,0 +1,291 @@
+use std::fmt;
+
+use crate::{
+    balance::Balance,
+    circle::Circle,
+    clearance::Clearance,
+    container::Container,
+    utils::{cast, to_float_type, Float, FloatType},
+};
+
+/// Pair of inner circles intersecting each other.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Overlap<T: Float = FloatType> {
+    pub first: usize,
+    pub second: usize,
+    /// `r_i + r_j + clearance - distance` between the circles centers.
+    pub depth: T,
+}
+
+/// Inner circle sticking out of the container.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContainmentViolation<T: Float = FloatType> {
+    pub index: usize,
+    /// How far the circle sticks out of the container shrunk by the wall clearance.
+    pub excess: T,
+}
+
+/// Every constraint violated by a packing by more than `tolerance`.
+///
+/// Circles are referred by their position in the checked slice.
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport<T: Float = FloatType> {
+    pub tolerance: T,
+    pub overlaps: Vec<Overlap<T>>,
+    pub containment_violations: Vec<ContainmentViolation<T>>,
+    pub missing_centers: Vec<usize>,
+    /// Inner circles intersecting obstacles, `second` is the obstacle index.
+    pub obstacle_overlaps: Vec<Overlap<T>>,
+    /// Distance between the centre of mass of circles and the container centre, circles
+    /// areas are used as masses unless checked against a balance constraint.
+    pub imbalance: T,
+    /// Allowed imbalance of balanced packings.
+    pub max_imbalance: Option<T>,
+    /// Maximum violation of any constraint, including ones within tolerance.
+    pub max_violation: T,
+}
+
+impl<T: Float> ValidationReport<T> {
+    pub fn new(container: &Container<T>, circles: &[Circle<T>], tolerance: T) -> Self {
+        Self::with_obstacles(container, circles, &[], tolerance)
+    }
+
+    pub fn with_obstacles(
+        container: &Container<T>,
+        circles: &[Circle<T>],
+        obstacles: &[Circle<T>],
+        tolerance: T,
+    ) -> Self {
+        Self::with_clearance(
+            container,
+            circles,
+            obstacles,
+            &Clearance::default(),
+            tolerance,
+        )
+    }
+
+    pub fn with_clearance(
+        container: &Container<T>,
+        circles: &[Circle<T>],
+        obstacles: &[Circle<T>],
+        clearance: &Clearance<T>,
+        tolerance: T,
+    ) -> Self {
+        let mut report = Self {
+            tolerance,
+            imbalance: Balance::default().imbalance(circles),
+            ..Default::default()
+        };
+
+        for (index, circle) in circles.iter().enumerate() {
+            match &circle.center {
+                Some(center) => {
+                    let excess = container.excess(
+                        center.clone(),
+                        circle.radius.clone() + clearance.wall.clone(),
+                    );
+                    report.max_violation = report.max_violation.clone().max(excess.clone());
+
+                    if excess > report.tolerance {
+                        report
+                            .containment_violations
+                            .push(ContainmentViolation { index, excess });
+                    }
+                }
+                None => report.missing_centers.push(index),
+            }
+        }
+
+        for i in 0..circles.len() {
+            for j in i + 1..circles.len() {
+                if circles[i].center.is_none() || circles[j].center.is_none() {
+                    continue;
+                }
+
+                let depth =
+                    circles[i].radius.clone() + circles[j].radius.clone() + clearance.pair.clone()
+                        - circles[i].distance(&circles[j]);
+                report.max_violation = report.max_violation.clone().max(depth.clone());
+
+                if depth > report.tolerance {
+                    report.overlaps.push(Overlap {
+                        first: i,
+                        second: j,
+                        depth,
+                    });
+                }
+            }
+        }
+
+        for (i, circle) in circles.iter().enumerate() {
+            if circle.center.is_none() {
+                continue;
+            }
+
+            for (j, obstacle) in obstacles.iter().enumerate() {
+                let depth =
+                    circle.radius.clone() + obstacle.radius.clone() + clearance.pair.clone()
+                        - circle.distance(obstacle);
+                report.max_violation = report.max_violation.clone().max(depth.clone());
+
+                if depth > report.tolerance {
+                    report.obstacle_overlaps.push(Overlap {
+                        first: i,
+                        second: j,
+                        depth,
+                    });
+                }
+            }
+        }
+
+        report
+    }
+
+    /// Check the centre of mass of circles against the balance constraint, masses which do
+    /// not match circles make the imbalance infinite.
+    pub fn with_balance(mut self, circles: &[Circle<T>], balance: &Balance<T>) -> Self {
+        self.imbalance = if balance.fits(circles.len()) {
+            balance.imbalance(circles)
+        } else {
+            cast(FloatType::INFINITY)
+        };
+        self.max_imbalance = Some(balance.max_offset.clone());
+        self.max_violation = self
+            .max_violation
+            .clone()
+            .max(self.imbalance.clone() - balance.max_offset.clone());
+
+        self
+    }
+
+    pub fn is_balanced(&self) -> bool {
+        self.max_imbalance.as_ref().is_none_or(|max_imbalance| {
+            self.imbalance.clone() - max_imbalance.clone() <= self.tolerance
+        })
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.overlaps.is_empty()
+            && self.containment_violations.is_empty()
+            && self.missing_centers.is_empty()
+            && self.obstacle_overlaps.is_empty()
+            && self.is_balanced()
+    }
+
+    /// Positions of circles taking part in any violation.
+    pub fn violating_circles(&self) -> Vec<usize> {
+        let mut indexes: Vec<usize> = self
+            .overlaps
+            .iter()
+            .flat_map(|overlap| [overlap.first, overlap.second])
+            .chain(self.containment_violations.iter().map(|v| v.index))
+            .chain(self.missing_centers.iter().copied())
+            .chain(self.obstacle_overlaps.iter().map(|overlap| overlap.first))
+            .collect();
+
+        indexes.sort_unstable();
+        indexes.dedup();
+        indexes
+    }
+}
+
+impl<T: Float> fmt::Display for ValidationReport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "valid = {} (tolerance = {:e}, max violation = {:e}, overlaps = {}, outside = {}, missing centers = {}, obstacle overlaps = {}, imbalance = {:e})",
+            self.is_valid(),
+            to_float_type(self.tolerance.clone()),
+            to_float_type(self.max_violation.clone()),
+            self.overlaps.len(),
+            self.containment_violations.len(),
+            self.missing_centers.len(),
+            self.obstacle_overlaps.len(),
+            to_float_type(self.imbalance.clone())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point::Point;
+
+    fn circle(radius: FloatType, x: FloatType, y: FloatType) -> Circle {
+        Circle::new(radius, Point { x, y })
+    }
+
+    /// Report on a packing with an overlap of depth 0.5, a circle sticking out of the container
+    /// by 0.25 and an obstacle overlap of depth 0.75, all counting the clearance.
+    fn report(tolerance: FloatType) -> ValidationReport {
+        let circles = [
+            circle(2.0, 0.0, 0.0),
+            circle(1.0, 3.0, 0.0),
+            circle(1.0, 8.25, 0.0),
+            Circle::with_radius(1.0),
+        ];
+        let obstacles = [circle(1.0, 0.0, -2.75)];
+
+        ValidationReport::with_clearance(
+            &Container::circle(10.0),
+            &circles,
+            &obstacles,
+            &Clearance::new(0.5, 1.0),
+            tolerance,
+        )
+    }
+
+    #[test]
+    fn violations_are_measured_with_clearance() {
+        let report = report(0.0);
+
+        assert_eq!(
+            report.overlaps,
+            [Overlap {
+                first: 0,
+                second: 1,
+                depth: 0.5
+            }]
+        );
+        assert_eq!(
+            report.containment_violations,
+            [ContainmentViolation {
+                index: 2,
+                excess: 0.25
+            }]
+        );
+        assert_eq!(
+            report.obstacle_overlaps,
+            [Overlap {
+                first: 0,
+                second: 0,
+                depth: 0.75
+            }]
+        );
+        assert_eq!(report.missing_centers, [3]);
+        assert_eq!(report.max_violation, 0.75);
+        assert_eq!(report.violating_circles(), [0, 1, 2, 3]);
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn violations_within_tolerance_are_not_reported() {
+        let eps = 1e-9;
+
+        assert_eq!(report(0.25 - eps).containment_violations.len(), 1);
+        let within = report(0.25);
+        assert!(within.containment_violations.is_empty());
+        assert_eq!(within.overlaps.len(), 1);
+
+        assert_eq!(report(0.5 - eps).overlaps.len(), 1);
+        assert!(report(0.5).overlaps.is_empty());
+
+        assert_eq!(report(0.75 - eps).obstacle_overlaps.len(), 1);
+        let within = report(0.75);
+        assert!(within.obstacle_overlaps.is_empty());
+        // Violations within tolerance still count towards the maximum one.
+        assert_eq!(within.max_violation, 0.75);
+        assert_eq!(within.missing_centers, [3]);
+    }
+}